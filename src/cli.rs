@@ -1,10 +1,11 @@
 use std::{io::IsTerminal, path::PathBuf};
 
 use clap::{Parser, Subcommand};
+use tracing::level_filters::LevelFilter;
 use tracing_subscriber::EnvFilter;
 
 use crate::{
-    cmd::{build, index, serve},
+    cmd::{build, check, index, serve},
     JellyError,
 };
 
@@ -35,11 +36,35 @@ struct Build {
     /// Sanitize the HTML.
     #[arg(short = 'z', long, default_value_t = false)]
     sanitize: bool,
+
+    /// Fail if there are any warnings, like code blocks in languages that can't be highlighted.
+    #[arg(long, default_value_t = false)]
+    strict: bool,
 }
 
 impl Cmd for Build {
     fn execute(&self) -> Result<(), JellyError> {
-        build(self.source.clone(), self.out.clone(), self.sanitize)
+        build(
+            self.source.clone(),
+            self.out.clone(),
+            self.sanitize,
+            self.strict,
+        )
+    }
+}
+
+/// Check a Jelly docs project for broken internal links and images
+#[derive(Parser)]
+#[command(alias = "c", alias = "chk")]
+struct Check {
+    /// The root content directory
+    #[arg(short, long, default_value = "./docs")]
+    source: PathBuf,
+}
+
+impl Cmd for Check {
+    fn execute(&self) -> Result<(), JellyError> {
+        check(self.source.clone())
     }
 }
 
@@ -95,6 +120,7 @@ impl Cmd for Index {
 #[derive(Subcommand)]
 enum Command {
     Build(Build),
+    Check(Check),
     Index(Index),
     Serve(Serve),
 }
@@ -103,7 +129,12 @@ impl Cli {
     pub fn execute(self) -> color_eyre::Result<()> {
         tracing_subscriber::fmt()
             .with_ansi(true)
-            .with_env_filter(EnvFilter::from_default_env())
+            // Warnings (broken links, for example) are shown unless RUST_LOG says otherwise
+            .with_env_filter(
+                EnvFilter::builder()
+                    .with_default_directive(LevelFilter::WARN.into())
+                    .from_env_lossy(),
+            )
             .init();
 
         color_eyre::config::HookBuilder::default()
@@ -116,6 +147,7 @@ impl Cli {
 
         Ok(match self.command {
             Command::Build(build) => build.execute(),
+            Command::Check(check) => check.execute(),
             Command::Index(index) => index.execute(),
             Command::Serve(serve) => serve.execute(),
         }?)
@@ -1,18 +1,35 @@
 use std::path::PathBuf;
 
-use crate::{config::SiteConfig, content::Site, error::JellyError};
+use crate::{config::SiteConfig, content::Site, error::JellyError, md::Renderer};
 
-pub fn build(source: PathBuf, out: PathBuf, sanitize: bool) -> Result<(), JellyError> {
-    Site::write(&SiteConfig::new(source), out, sanitize)
+pub fn build(
+    source: PathBuf,
+    out: PathBuf,
+    sanitize: bool,
+    strict: bool,
+) -> Result<(), JellyError> {
+    let config = SiteConfig::load(source)?;
+    let site = Site::build(&config)?;
+    let warnings = site.warnings().len();
+
+    // A failed strict build doesn't leave any output behind
+    if strict && warnings > 0 {
+        return Err(JellyError::Warnings(warnings));
+    }
+
+    site.write_to(&config, &Renderer::new(&config)?, out, sanitize)
 }
 
 #[cfg(test)]
 mod tests {
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
+
+    use super::build;
 
     use crate::{
         config::SiteConfig,
         content::{Link, Page, Section, Site},
+        error::JellyError,
         md::{SearchDocument, SearchIndex, TableOfContents, TocEntry},
     };
 
@@ -28,14 +45,17 @@ mod tests {
                         Page::new(
                             "tests/full/basic/contact.md",
                             "contact.md",
-                            "contact",
+                            "/contact",
                             "Contact us",
                             "", // Omit for testing
                             "", // Omit for testing
-                            vec![Link::new(&PathBuf::from("tests/full/basic"), "Welcome")],
+                            vec![Link::new(Some("/"), "Welcome")],
                             TableOfContents::new(vec![]),
                             SearchIndex(vec![SearchDocument::new(
                                 1,
+                                "/contact",
+                                None,
+                                &[String::from("Welcome")],
                                 "Contact us",
                                 "Contact us",
                                 "Send us a fax.",
@@ -45,11 +65,11 @@ mod tests {
                         Page::new(
                             "tests/full/basic/index.md",
                             "index.md",
-                            "",
+                            "/",
                             "Welcome",
                             "", // Omit for testing
                             "", // Omit for testing
-                            vec![Link::new(&PathBuf::from("tests/full/basic"), "Welcome")],
+                            vec![Link::new(Some("/"), "Welcome")],
                             TableOfContents::new(vec![TocEntry::new(
                                 2,
                                 "About this site",
@@ -58,12 +78,18 @@ mod tests {
                             SearchIndex(vec![
                                 SearchDocument::new(
                                     1,
+                                    "/",
+                                    None,
+                                    &[String::from("Welcome")],
                                     "Welcome",
                                     "Welcome",
                                     "Welcome to the site.",
                                 ),
                                 SearchDocument::new(
                                     2,
+                                    "/",
+                                    Some("about-this-site"),
+                                    &[String::from("Welcome")],
                                     "Welcome",
                                     "About this site",
                                     "Some info here.",
@@ -74,14 +100,17 @@ mod tests {
                         Page::new(
                             "tests/full/basic/about.md",
                             "about.md",
-                            "about",
+                            "/about",
                             "About",
                             "", // Omit for testing
                             "", // Omit for testing
-                            vec![Link::new(&PathBuf::from("tests/full/basic"), "Welcome")],
+                            vec![Link::new(Some("/"), "Welcome")],
                             TableOfContents::new(vec![]),
                             SearchIndex(vec![SearchDocument::new(
                                 1,
+                                "/about",
+                                None,
+                                &[String::from("Welcome")],
                                 "About",
                                 "About",
                                 "About this thing.",
@@ -100,22 +129,35 @@ mod tests {
                     Some(vec![Page::new(
                         "tests/full/medium/index.md",
                         "index.md",
-                        "",
+                        "/",
                         "Welcome",
                         "", // Omit for testing
                         "", // Omit for testing
-                        vec![Link::new(
-                            &PathBuf::from("tests/full/medium"),
-                            "Medium-sized project",
-                        )],
+                        vec![Link::new(Some("/"), "Medium-sized project")],
                         TableOfContents::new(vec![TocEntry::new(
                             2,
                             "About this site",
                             TableOfContents::empty(),
                         )]),
                         SearchIndex(vec![
-                            SearchDocument::new(1, "Welcome", "Welcome", "Welcome to the site."),
-                            SearchDocument::new(2, "Welcome", "About this site", "Some info here."),
+                            SearchDocument::new(
+                                1,
+                                "/",
+                                None,
+                                &[String::from("Medium-sized project")],
+                                "Welcome",
+                                "Welcome",
+                                "Welcome to the site.",
+                            ),
+                            SearchDocument::new(
+                                2,
+                                "/",
+                                Some("about-this-site"),
+                                &[String::from("Medium-sized project")],
+                                "Welcome",
+                                "About this site",
+                                "Some info here.",
+                            ),
                         ]),
                         None,
                     )]),
@@ -125,20 +167,20 @@ mod tests {
                         Some(vec![Page::new(
                             "tests/full/medium/setup/index.md",
                             "setup/index.md",
-                            "setup",
+                            "/setup",
                             "Setup",
                             "", // Omit for testing
                             "", // Omit for testing
                             vec![
-                                Link::new(
-                                    &PathBuf::from("tests/full/medium"),
-                                    "Medium-sized project",
-                                ),
-                                Link::new(&PathBuf::from("tests/full/medium/setup"), "Setup"),
+                                Link::new(Some("/"), "Medium-sized project"),
+                                Link::new(Some("/setup"), "Setup"),
                             ],
                             TableOfContents::empty(),
                             SearchIndex(vec![SearchDocument::new(
                                 1,
+                                "/setup",
+                                None,
+                                &[String::from("Medium-sized project"), String::from("Setup")],
                                 "Setup",
                                 "Setup",
                                 "Here is how to set things up. Here is some other info.",
@@ -162,6 +204,7 @@ mod tests {
 
                 assert_eq!(page.path, expected.path);
                 assert_eq!(page.relative_path, expected.relative_path);
+                assert_eq!(page.url, expected.url);
                 assert_eq!(page.title, expected.title);
                 assert_eq!(page.breadcrumb, expected.breadcrumb);
                 assert_eq!(page.table_of_contents, expected.table_of_contents);
@@ -170,4 +213,186 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn write_search_index() {
+        let out = tempfile::tempdir().unwrap();
+        let config = SiteConfig::new(PathBuf::from("tests/full/medium"));
+
+        Site::write(&config, out.path().to_path_buf(), false).unwrap();
+
+        let json = std::fs::read_to_string(out.path().join("search-index.json")).unwrap();
+        let documents: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+
+        let ids: Vec<&str> = documents
+            .iter()
+            .map(|d| d["id"].as_str().unwrap())
+            .collect();
+        assert_eq!(ids, vec!["/", "/#about-this-site", "/setup"]);
+
+        let html = std::fs::read_to_string(out.path().join("index.html")).unwrap();
+        assert!(html.contains("search('/search-index.json')"));
+    }
+
+    #[test]
+    fn track_included_files() {
+        let config = SiteConfig::new(PathBuf::from("tests/full/includes/docs"));
+        let site = Site::build(&config).unwrap();
+
+        assert_eq!(
+            site.included_files(),
+            vec![PathBuf::from(
+                "tests/full/includes/docs/../examples/main.rs"
+            )]
+        );
+        assert!(site.pages()[0].html.contains("config"));
+    }
+
+    #[test]
+    fn copy_static_assets() {
+        let out = tempfile::tempdir().unwrap();
+        let config = SiteConfig::load(PathBuf::from("tests/full/assets")).unwrap();
+
+        Site::write(&config, out.path().to_path_buf(), false).unwrap();
+
+        for file in [
+            "index.html",
+            "guide/index.html",
+            "guide/diagram.svg",
+            "guide/example.rs",
+            "images/logo.svg",
+            "robots.txt",
+            "downloads/example.txt",
+        ] {
+            assert!(out.path().join(file).is_file(), "{file} is missing");
+        }
+
+        for file in ["jelly.yaml", "index.md", "guide/index.md", "static"] {
+            assert!(
+                !out.path().join(file).exists(),
+                "{file} shouldn't be copied"
+            );
+        }
+
+        let html = std::fs::read_to_string(out.path().join("guide/index.html")).unwrap();
+        assert!(html.contains("src=\"/guide/diagram.svg\""));
+        assert!(html.contains("src=\"/images/logo.svg\""));
+    }
+
+    #[test]
+    fn rewrite_relative_links() {
+        let config = SiteConfig::new(PathBuf::from("tests/full/links"));
+        let site = Site::build(&config).unwrap();
+
+        let cases: Vec<(&str, Vec<&str>)> = vec![
+            (
+                "/",
+                vec![
+                    "<a href=\"/guides/setup#install\">setup guide</a>",
+                    "<a href=\"/guides\">guides</a>",
+                    // Left alone (and reported) since the target doesn't exist
+                    "<a href=\"nope.md\">broken link</a>",
+                ],
+            ),
+            (
+                "/guides",
+                vec![
+                    "<a href=\"/\">start</a>",
+                    "<a href=\"/guides/setup\">setup</a>",
+                ],
+            ),
+            (
+                "/guides/setup",
+                vec!["<a href=\"/guides/files/example.txt?raw=1\">example</a>"],
+            ),
+        ];
+
+        for (url, links) in cases {
+            let page = site.pages().into_iter().find(|p| p.url == url).unwrap();
+
+            for link in links {
+                assert!(page.html.contains(link), "{url} lacks {link}");
+            }
+        }
+    }
+
+    #[test]
+    fn page_urls_resolve_to_files() {
+        for name in ["basic", "includes/docs", "links", "medium", "ordered"] {
+            let source = PathBuf::from("tests/full").join(name);
+            let out = tempfile::tempdir().unwrap();
+
+            build(source.clone(), out.path().to_path_buf(), false, false).unwrap();
+
+            // The way a plain static file server resolves a URL
+            let resolves = |url: &str| {
+                let path = out.path().join(url.trim_start_matches('/'));
+                path.is_file() || path.join("index.html").is_file()
+            };
+
+            for page in Site::build(&SiteConfig::load(source).unwrap())
+                .unwrap()
+                .pages()
+            {
+                assert!(resolves(&page.url), "{name}: nothing at {}", page.url);
+
+                // Sidebar, breadcrumb and content links to other pages (links to files are left
+                // as they are)
+                let html =
+                    std::fs::read_to_string(page.html_path(out.path().to_path_buf())).unwrap();
+                for href in html.split("href=\"/").skip(1) {
+                    let url = href.split(['"', '#', '?']).next().unwrap();
+                    if Path::new(url).extension().is_none() {
+                        assert!(resolves(url), "{name}: {} links to /{url}", page.url);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn sanitized_build() {
+        let out = tempfile::tempdir().unwrap();
+
+        build(
+            PathBuf::from("tests/full/basic"),
+            out.path().to_path_buf(),
+            true,
+            false,
+        )
+        .unwrap();
+
+        let html = std::fs::read_to_string(out.path().join("index.html")).unwrap();
+        assert!(html.contains("<h2 id=\"about-this-site\">About this site"));
+        assert!(html.contains("<a href=\"#about-this-site\" class=\"heading-anchor\""));
+    }
+
+    #[test]
+    fn strict_build() {
+        let source = PathBuf::from("tests/full/warnings");
+        let strict_out = tempfile::tempdir().unwrap();
+        let out = tempfile::tempdir().unwrap();
+
+        let site = Site::build(&SiteConfig::new(source.clone())).unwrap();
+        assert_eq!(
+            site.warnings(),
+            vec![
+                "tests/full/warnings/index.md:7: unresolved link to nope.md",
+                "tests/full/warnings/index.md:9: no syntax for code block language jdsl, so it isn't highlighted",
+                "tests/full/warnings/index.md:15: heading id setup is already used on the page",
+                "tests/full/warnings/index.md:17: missing closing brace in math \\frac{",
+            ]
+        );
+
+        assert!(matches!(
+            build(source.clone(), strict_out.path().join("dist"), false, true),
+            Err(JellyError::Warnings(4))
+        ));
+        assert!(!strict_out.path().join("dist").exists());
+
+        assert!(build(source, out.path().to_path_buf(), false, false).is_ok());
+
+        let html = std::fs::read_to_string(out.path().join("index.html")).unwrap();
+        assert!(html.contains("if x &lt; 1 {"));
+    }
 }
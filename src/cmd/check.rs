@@ -0,0 +1,86 @@
+use std::path::PathBuf;
+
+use crate::{
+    config::SiteConfig,
+    content::{Problem, Site},
+    error::JellyError,
+};
+
+pub fn check(source: PathBuf) -> Result<(), JellyError> {
+    let config = SiteConfig::load(source)?;
+    let problems = Site::build(&config)?.check(&config);
+
+    if problems.is_empty() {
+        println!("no broken links found");
+        return Ok(());
+    }
+
+    print!("{}", report(&problems));
+
+    Err(JellyError::BrokenLinks(problems.len()))
+}
+
+// Problems are grouped by file (they come sorted by file and line)
+fn report(problems: &[Problem]) -> String {
+    let mut out = String::new();
+    let mut current_file: Option<&str> = None;
+
+    for problem in problems {
+        if current_file != Some(&problem.file) {
+            if current_file.is_some() {
+                out.push('\n');
+            }
+            out.push_str(&format!("{}\n", problem.file));
+            current_file = Some(&problem.file);
+        }
+
+        let location = match problem.line {
+            Some(line) => format!("{}:{line}", problem.file),
+            None => problem.file.clone(),
+        };
+
+        out.push_str(&format!("  {location}: {}\n", problem.message));
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::content::Problem;
+
+    use super::report;
+
+    #[test]
+    fn grouped_report() {
+        let problem = |file: &str, line: Option<usize>, message: &str| Problem {
+            file: String::from(file),
+            line,
+            message: String::from(message),
+        };
+
+        let problems = vec![
+            problem(
+                "docs/a.md",
+                Some(3),
+                "link to nope.md doesn't lead to a page",
+            ),
+            problem("docs/a.md", Some(9), "image /x.png doesn't exist"),
+            problem("docs/b.md", None, "no heading with id \"foo\" on /a"),
+        ];
+
+        assert_eq!(
+            report(&problems),
+            indoc! {r#"
+                docs/a.md
+                  docs/a.md:3: link to nope.md doesn't lead to a page
+                  docs/a.md:9: image /x.png doesn't exist
+
+                docs/b.md
+                  docs/b.md: no heading with id "foo" on /a
+            "#}
+        );
+    }
+}
@@ -3,7 +3,7 @@ use std::path::PathBuf;
 use crate::{config::SiteConfig, content::Site, error::JellyError, utils::write_file};
 
 pub fn index(source: PathBuf, out: Option<PathBuf>) -> Result<(), JellyError> {
-    let config = SiteConfig::new(source);
+    let config = SiteConfig::load(source)?;
 
     let index = Site::build(&config)?.index();
     let json = serde_json::to_string(&index)?;
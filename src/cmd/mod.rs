@@ -1,7 +1,9 @@
 mod build;
+mod check;
 mod index;
 mod serve;
 
 pub use build::build;
+pub use check::check;
 pub use index::index;
 pub use serve::serve;
@@ -1,10 +1,12 @@
 use crate::{config::SiteConfig, content::Site as Buildable, error::JellyError};
 use indoc::{formatdoc, indoc};
-use notify::{Event, Watcher};
+use notify::{Event, RecursiveMode, Watcher};
 use std::{
+    collections::HashSet,
     fmt::Debug,
+    fs::canonicalize,
     net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener},
-    path::PathBuf,
+    path::{Path, PathBuf},
     process::exit,
     str::FromStr,
     sync::mpsc::channel,
@@ -19,19 +21,28 @@ const LIVE_RELOAD_JS: &str = include_str!("../../assets/livereload.js");
 
 struct Site {
     out_path: PathBuf,
-    config: SiteConfig,
+    source: PathBuf,
 }
 
 impl Site {
-    fn new(out_path: PathBuf, config: SiteConfig) -> Self {
-        Self { out_path, config }
+    fn new(out_path: PathBuf, source: PathBuf) -> Self {
+        Self { out_path, source }
     }
 
-    fn build(&self) {
+    // Returns the files that code is included from, which need to be watched as well
+    fn build(&self) -> Vec<PathBuf> {
         debug!("building site");
 
-        if let Err(e) = Buildable::write(&self.config, self.out_path.clone(), false) {
-            error!("error building site: {e}");
+        // The config is reloaded on every build so that changes to jelly.yaml are picked up
+        let result = SiteConfig::load(self.source.clone())
+            .and_then(|config| Buildable::write(&config, self.out_path.clone(), false));
+
+        match result {
+            Ok(site) => site.included_files(),
+            Err(e) => {
+                error!("error building site: {e}");
+                Vec::new()
+            }
         }
     }
 }
@@ -73,10 +84,11 @@ impl FileServer {
 
             let path = self.root.to_path_buf().join(&req_path[1..]);
 
-            let serve_path = if path.is_file() {
-                path
-            } else {
+            // Page URLs point at directories with an index.html
+            let serve_path = if path.is_dir() {
                 path.join("index.html")
+            } else {
+                path
             };
 
             if serve_path.exists() {
@@ -128,8 +140,25 @@ pub fn serve(source: PathBuf, open: bool, port: u16) -> Result<(), JellyError> {
         out_path.display().to_string()
     );
 
-    let site = Site::new(out_path.clone(), SiteConfig::new(source.clone()));
-    site.build();
+    let config = SiteConfig::load(source.clone())?;
+
+    // jelly.yaml and the templates, static and syntaxes directories usually live outside of the
+    // source directory. Every build reloads the config and loads the syntaxes again, so changes to
+    // any of them show up.
+    let project_paths: Vec<PathBuf> = config
+        .config_file
+        .into_iter()
+        .chain(
+            [config.templates_dir, config.static_dir]
+                .into_iter()
+                .chain(config.syntaxes_dir)
+                .filter(|dir| dir.is_dir()),
+        )
+        .filter(|path| !path.starts_with(&source))
+        .collect();
+
+    let site = Site::new(out_path.clone(), source.clone());
+    let included_files = site.build();
 
     if open {
         open::that(format!("http://localhost:{port}"))?;
@@ -182,59 +211,105 @@ pub fn serve(source: PathBuf, open: bool, port: u16) -> Result<(), JellyError> {
 
     debug!("setting up watcher on {:?}", &source);
 
-    let (_tx, rx) = channel::<Event>();
+    // Events are handled here rather than in the watcher's callback so that files newly
+    // included by pages can be added to the watcher after each rebuild
+    let (tx, rx) = channel::<Event>();
     let mut watcher = notify::recommended_watcher(move |res| match res {
-        Ok(Event { kind, paths, .. }) => {
-            use notify::EventKind::*;
+        Ok(event) => handle_error(tx.send(event)),
+        Err(e) => println!("watch error: {:?}", e),
+    })?;
 
-            match kind {
-                Create(_) | Modify(_) | Remove(_) => {
-                    debug!("got a {kind:?} event");
+    debug!("set up watcher on {:?}", source);
 
-                    debug!("rebuilding site");
+    #[cfg(not(feature = "dev-handlebars-templates"))]
+    let mut watch_paths = vec![source];
 
-                    site.build();
+    #[cfg(feature = "dev-handlebars-templates")]
+    let mut watch_paths = vec![source, "assets/templates".into()];
 
-                    debug!("successfully rebuilt site");
+    watch_paths.extend(project_paths);
 
-                    debug!("broadcaster sending message");
+    for path in &watch_paths {
+        watcher.watch(path.as_path(), RecursiveMode::Recursive)?;
+    }
 
-                    if let Some(path) = paths.first() {
-                        handle_error(broadcaster.send(live_reload_message(path)));
-                    }
+    let watched_dirs: Vec<PathBuf> = watch_paths
+        .iter()
+        .map(|path| canonicalize(path).unwrap_or_else(|_| path.clone()))
+        .collect();
+    let mut watched_files: HashSet<PathBuf> = HashSet::new();
+
+    watch_included_files(
+        &mut watcher,
+        &watched_dirs,
+        &mut watched_files,
+        included_files,
+    );
 
-                    debug!("broadcaster sent message");
-                }
-                _ => {
-                    debug!("got some other kind of event: {:?}", kind);
-                }
+    loop {
+        let Event { kind, paths, .. } = match rx.recv() {
+            Ok(event) => event,
+            Err(e) => {
+                debug!("deleting temporary directory {tmp_dir:?}",);
+                tmp_dir.close()?;
+                debug!("error encountered from listener: {}", e);
+                return Err(JellyError::Recv(e));
             }
-        }
-        Err(e) => println!("watch error: {:?}", e),
-    })?;
+        };
 
-    debug!("set up watcher on {:?}", source);
+        use notify::EventKind::*;
 
-    #[cfg(not(feature = "dev-handlebars-templates"))]
-    let watch_paths = vec![source];
+        match kind {
+            Create(_) | Modify(_) | Remove(_) => {
+                debug!("got a {kind:?} event");
 
-    #[cfg(feature = "dev-handlebars-templates")]
-    let watch_paths = vec![source, "assets/templates".into()];
+                debug!("rebuilding site");
 
-    for path in watch_paths {
-        watcher.watch(path.as_path(), notify::RecursiveMode::Recursive)?;
-    }
+                let included_files = site.build();
 
-    if let Err(e) = rx.recv() {
-        debug!("deleting temporary directory {tmp_dir:?}",);
-        tmp_dir.close()?;
-        debug!("error encountered from listener: {}", e);
-        return Err(JellyError::Recv(e));
+                debug!("successfully rebuilt site");
+
+                watch_included_files(
+                    &mut watcher,
+                    &watched_dirs,
+                    &mut watched_files,
+                    included_files,
+                );
+
+                debug!("broadcaster sending message");
+
+                if let Some(path) = paths.first() {
+                    handle_error(broadcaster.send(live_reload_message(path)));
+                }
+
+                debug!("broadcaster sent message");
+            }
+            _ => {
+                debug!("got some other kind of event: {:?}", kind);
+            }
+        }
     }
+}
+
+// Included files usually live outside the content directory (in an examples crate, say)
+fn watch_included_files(
+    watcher: &mut impl Watcher,
+    watched_dirs: &[PathBuf],
+    watched_files: &mut HashSet<PathBuf>,
+    files: Vec<PathBuf>,
+) {
+    for file in files {
+        let file = canonicalize(&file).unwrap_or(file);
+
+        if watched_dirs.iter().any(|dir| file.starts_with(dir)) || watched_files.contains(&file) {
+            continue;
+        }
 
-    debug!("quitting");
+        debug!("watching included file {file:?}");
 
-    Ok(())
+        handle_error(watcher.watch(Path::new(&file), RecursiveMode::NonRecursive));
+        watched_files.insert(file);
+    }
 }
 
 fn live_reload_message(path: &PathBuf) -> String {
@@ -0,0 +1,57 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use serde::Deserialize;
+
+// The themes that ship with syntect
+pub(crate) const THEMES: [&str; 7] = [
+    "InspiredGitHub",
+    "Solarized (light)",
+    "Solarized (dark)",
+    "base16-ocean.light",
+    "base16-ocean.dark",
+    "base16-eighties.dark",
+    "base16-mocha.dark",
+];
+
+// Syntax highlighting themes, by their syntect names, e.g.
+//
+//   highlighting:
+//     theme: Solarized (light)
+//     dark_theme: Solarized (dark)
+//     syntaxes: syntaxes
+//     aliases:
+//       jsonc: json
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct HighlightingConfig {
+    pub(crate) theme: String,
+    // Only used with classes since inline styles can't change with dark mode
+    pub(crate) dark_theme: String,
+    pub(crate) mode: HighlightMode,
+    // Directory of extra `.sublime-syntax` files, relative to the directory containing jelly.yaml
+    pub(crate) syntaxes: Option<PathBuf>,
+    // Other names for languages
+    pub(crate) aliases: BTreeMap<String, String>,
+}
+
+impl Default for HighlightingConfig {
+    fn default() -> Self {
+        Self {
+            theme: String::from("InspiredGitHub"),
+            dark_theme: String::from("base16-ocean.dark"),
+            mode: HighlightMode::default(),
+            syntaxes: None,
+            aliases: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum HighlightMode {
+    // Classes styled by a generated stylesheet
+    #[default]
+    Classes,
+    // Colors in `style` attributes, for HTML that gets copied elsewhere (such as into emails)
+    Inline,
+}
@@ -0,0 +1,56 @@
+use serde::Deserialize;
+
+// Markdown extensions beyond CommonMark that projects can opt out of in jelly.yaml, e.g.
+//
+//   markdown:
+//     footnotes: false
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct MarkdownConfig {
+    pub(crate) footnotes: bool,
+    pub(crate) definition_lists: bool,
+    pub(crate) task_lists: bool,
+    pub(crate) abbreviations: bool,
+    pub(crate) heading_anchors: HeadingAnchorsConfig,
+}
+
+impl Default for MarkdownConfig {
+    fn default() -> Self {
+        Self {
+            footnotes: true,
+            definition_lists: true,
+            task_lists: true,
+            abbreviations: true,
+            heading_anchors: HeadingAnchorsConfig::default(),
+        }
+    }
+}
+
+// The link to each heading's anchor, which themes style through its class
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct HeadingAnchorsConfig {
+    pub(crate) enabled: bool,
+    pub(crate) symbol: String,
+    pub(crate) class: String,
+    pub(crate) position: AnchorPosition,
+}
+
+impl Default for HeadingAnchorsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            symbol: String::from("#"),
+            class: String::from("heading-anchor"),
+            position: AnchorPosition::default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum AnchorPosition {
+    Before,
+    #[default]
+    After,
+}
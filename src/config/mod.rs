@@ -1,7 +1,11 @@
+mod highlighting;
+mod markdown;
 mod section;
 mod site;
 mod title;
 
+pub(super) use highlighting::{HighlightMode, HighlightingConfig, THEMES};
+pub(super) use markdown::{AnchorPosition, HeadingAnchorsConfig, MarkdownConfig};
 pub(super) use section::{SectionConfigInput, SectionConfigOutput};
-pub(super) use site::SiteConfig;
+pub(super) use site::{ColorsConfig, SearchProvider, SiteConfig};
 pub(super) use title::TitleConfig;
@@ -1,28 +1,395 @@
-use std::path::PathBuf;
+use std::{
+    fs::read_to_string,
+    path::{Path, PathBuf},
+};
 
-use super::TitleConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::error::JellyError;
+
+use super::{HighlightingConfig, MarkdownConfig, TitleConfig, THEMES};
 
 const DEFAULT_DOCS_DIR: &str = "docs";
+const CONFIG_FILE_NAME: &str = "jelly.yaml";
+const DEFAULT_TEMPLATES_DIR: &str = "templates";
+const DEFAULT_STATIC_DIR: &str = "static";
+const DEFAULT_PRIMARY_COLOR: &str = "#123456";
+const DEFAULT_SECONDARY_COLOR: &str = "#654321";
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct SiteConfigInput {
+    pub(crate) title: String,
+    pub(crate) description: Option<String>,
+    pub(crate) repo: Option<String>,
+    #[serde(default)]
+    pub(crate) colors: ColorsConfig,
+    #[serde(default)]
+    pub(crate) search: SearchProvider,
+    #[serde(default)]
+    pub(crate) markdown: MarkdownConfig,
+    #[serde(default)]
+    pub(crate) highlighting: HighlightingConfig,
+    // Relative to the directory containing jelly.yaml
+    pub(crate) templates: Option<PathBuf>,
+    // Relative to the directory containing jelly.yaml
+    #[serde(rename = "static")]
+    pub(crate) static_dir: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ColorsConfig {
+    #[serde(default = "default_primary_color")]
+    pub(crate) primary: String,
+    #[serde(default = "default_secondary_color")]
+    pub(crate) secondary: String,
+}
+
+impl Default for ColorsConfig {
+    fn default() -> Self {
+        Self {
+            primary: default_primary_color(),
+            secondary: default_secondary_color(),
+        }
+    }
+}
+
+fn default_primary_color() -> String {
+    String::from(DEFAULT_PRIMARY_COLOR)
+}
+
+fn default_secondary_color() -> String {
+    String::from(DEFAULT_SECONDARY_COLOR)
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum SearchProvider {
+    #[default]
+    Local,
+    Algolia,
+    Elastic,
+}
 
 pub(crate) struct SiteConfig {
     pub(crate) root: PathBuf,
+    pub(crate) config_file: Option<PathBuf>,
+    pub(crate) title: Option<String>,
+    pub(crate) description: Option<String>,
+    pub(crate) repo: Option<String>,
+    pub(crate) colors: ColorsConfig,
+    pub(crate) search: SearchProvider,
+    pub(crate) markdown: MarkdownConfig,
+    pub(crate) highlighting: HighlightingConfig,
+    pub(crate) templates_dir: PathBuf,
+    pub(crate) static_dir: PathBuf,
+    pub(crate) syntaxes_dir: Option<PathBuf>,
     pub(crate) title_config: TitleConfig,
 }
 
 impl SiteConfig {
     pub(crate) fn new(root: PathBuf) -> Self {
         Self {
+            templates_dir: project_dir(&root).join(DEFAULT_TEMPLATES_DIR),
+            static_dir: project_dir(&root).join(DEFAULT_STATIC_DIR),
             root: root.to_path_buf(),
-            title_config: TitleConfig::default(),
+            ..Self::default()
         }
     }
+
+    // Looks for a jelly.yaml in the content directory and then in its parent (the project root).
+    pub(crate) fn load(root: PathBuf) -> Result<Self, JellyError> {
+        match find_config_file(&root) {
+            Some(path) => Self::from_file(root, &path),
+            None => Ok(Self::new(root)),
+        }
+    }
+
+    pub(crate) fn from_file(root: PathBuf, path: &Path) -> Result<Self, JellyError> {
+        let yaml = read_to_string(path)?;
+        let input: SiteConfigInput = serde_yaml::from_str(&yaml)
+            .map_err(|e| JellyError::ConfigParse(path.to_path_buf(), e))?;
+
+        let config_dir = path.parent().unwrap_or(Path::new(""));
+        let templates_dir = config_dir.join(
+            input
+                .templates
+                .clone()
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_TEMPLATES_DIR)),
+        );
+        let static_dir = config_dir.join(
+            input
+                .static_dir
+                .clone()
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_STATIC_DIR)),
+        );
+
+        let syntaxes_dir = input
+            .highlighting
+            .syntaxes
+            .as_ref()
+            .map(|dir| config_dir.join(dir));
+
+        validate(&input, path)?;
+
+        // Explicitly configured directories need to exist
+        for (key, configured, dir) in [
+            ("templates", &input.templates, &templates_dir),
+            ("static", &input.static_dir, &static_dir),
+            (
+                "highlighting.syntaxes",
+                &input.highlighting.syntaxes,
+                &syntaxes_dir.clone().unwrap_or_default(),
+            ),
+        ] {
+            if configured.is_some() && !dir.is_dir() {
+                return Err(JellyError::InvalidConfig(
+                    path.to_path_buf(),
+                    String::from(key),
+                    format!("{} is not a directory", dir.display()),
+                ));
+            }
+        }
+
+        Ok(Self {
+            root,
+            config_file: Some(path.to_path_buf()),
+            title: Some(input.title),
+            description: input.description,
+            repo: input.repo,
+            colors: input.colors,
+            search: input.search,
+            markdown: input.markdown,
+            highlighting: input.highlighting,
+            templates_dir,
+            static_dir,
+            syntaxes_dir,
+            title_config: TitleConfig::default(),
+        })
+    }
+
+    // Directories inside the content root that hold project files rather than docs
+    pub(crate) fn is_reserved_dir(&self, path: &Path) -> bool {
+        path == self.templates_dir
+            || path == self.static_dir
+            || self.syntaxes_dir.as_deref() == Some(path)
+    }
 }
 
 impl Default for SiteConfig {
     fn default() -> Self {
         Self {
             root: PathBuf::from(DEFAULT_DOCS_DIR),
+            config_file: None,
+            title: None,
+            description: None,
+            repo: None,
+            colors: ColorsConfig::default(),
+            search: SearchProvider::default(),
+            markdown: MarkdownConfig::default(),
+            highlighting: HighlightingConfig::default(),
+            templates_dir: PathBuf::from(DEFAULT_TEMPLATES_DIR),
+            static_dir: PathBuf::from(DEFAULT_STATIC_DIR),
+            syntaxes_dir: None,
             title_config: TitleConfig::default(),
         }
     }
 }
+
+// Without a jelly.yaml, the project is assumed to be laid out as in `jelly init`, with the
+// content directory sitting in the project root.
+fn project_dir(root: &Path) -> PathBuf {
+    root.parent().map(Path::to_path_buf).unwrap_or_default()
+}
+
+fn find_config_file(root: &Path) -> Option<PathBuf> {
+    let mut candidates = vec![root.join(CONFIG_FILE_NAME)];
+
+    if let Some(parent) = root.parent() {
+        candidates.push(parent.join(CONFIG_FILE_NAME));
+    }
+
+    candidates.into_iter().find(|path| path.is_file())
+}
+
+fn validate(input: &SiteConfigInput, path: &Path) -> Result<(), JellyError> {
+    let invalid = |key: &str, reason: String| {
+        Err(JellyError::InvalidConfig(
+            path.to_path_buf(),
+            String::from(key),
+            reason,
+        ))
+    };
+
+    if input.title.trim().is_empty() {
+        return invalid("title", String::from("must not be empty"));
+    }
+
+    if let Some(repo) = &input.repo {
+        if !(repo.starts_with("https://") || repo.starts_with("http://")) {
+            return invalid("repo", format!("{repo:?} is not an http(s) URL"));
+        }
+    }
+
+    for (key, color) in [
+        ("colors.primary", &input.colors.primary),
+        ("colors.secondary", &input.colors.secondary),
+    ] {
+        if !is_valid_color(color) {
+            return invalid(key, format!("{color:?} is not a valid CSS color"));
+        }
+    }
+
+    for (key, theme) in [
+        ("highlighting.theme", &input.highlighting.theme),
+        ("highlighting.dark_theme", &input.highlighting.dark_theme),
+    ] {
+        if !THEMES.contains(&theme.as_str()) {
+            return invalid(key, format!("{theme:?} is not a known theme"));
+        }
+    }
+
+    Ok(())
+}
+
+// Accepts hex colors, named colors, and rgb()/hsl() functions. Anything that could break out of
+// the CSS custom property it ends up in is rejected.
+fn is_valid_color(color: &str) -> bool {
+    if let Some(hex) = color.strip_prefix('#') {
+        return [3, 4, 6, 8].contains(&hex.len()) && hex.chars().all(|c| c.is_ascii_hexdigit());
+    }
+
+    if ["rgb(", "rgba(", "hsl(", "hsla("]
+        .iter()
+        .any(|prefix| color.starts_with(prefix))
+    {
+        return color.ends_with(')')
+            && color[..color.len() - 1]
+                .chars()
+                .skip_while(|c| *c != '(')
+                .skip(1)
+                .all(|c| c.is_ascii_digit() || " ,.%/".contains(c));
+    }
+
+    !color.is_empty() && color.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::error::JellyError;
+
+    use super::{is_valid_color, SearchProvider, SiteConfig};
+
+    #[test]
+    fn load_config_file() {
+        let config = SiteConfig::load(PathBuf::from("tests/full/medium")).unwrap();
+
+        assert_eq!(
+            config.config_file,
+            Some(PathBuf::from("tests/full/medium/jelly.yaml"))
+        );
+        assert_eq!(config.title.as_deref(), Some("Medium-sized project"));
+        assert_eq!(
+            config.repo.as_deref(),
+            Some("https://github.com/lucperkins/jelly")
+        );
+        assert_eq!(config.colors.primary, "teal");
+        assert_eq!(config.colors.secondary, "#654321");
+        assert_eq!(config.search, SearchProvider::Local);
+        assert!(!config.markdown.abbreviations);
+        assert!(config.markdown.footnotes);
+        assert_eq!(config.markdown.heading_anchors.symbol, "§");
+        assert_eq!(config.markdown.heading_anchors.class, "heading-anchor");
+        assert_eq!(config.highlighting.theme, "Solarized (light)");
+        assert_eq!(config.highlighting.dark_theme, "base16-ocean.dark");
+    }
+
+    #[test]
+    fn missing_config_file() {
+        let config = SiteConfig::load(PathBuf::from("tests/full/basic")).unwrap();
+
+        assert!(config.config_file.is_none());
+        assert!(config.title.is_none());
+    }
+
+    #[test]
+    fn invalid_config_files() {
+        let cases: Vec<(&str, &str)> = vec![
+            ("description: no title", "missing field `title`"),
+            ("title: Foo\nsearhc: local", "unknown field `searhc`"),
+            (
+                "title: Foo\ncolors:\n  primry: red",
+                "unknown field `primry`",
+            ),
+            ("title: Foo\nsearch: google", "unknown variant `google`"),
+            (
+                "title: Foo\nmarkdown:\n  footnote: false",
+                "unknown field `footnote`",
+            ),
+            (
+                "title: Foo\nmarkdown:\n  heading_anchors:\n    position: left",
+                "unknown variant `left`",
+            ),
+            ("title: \"  \"", "title: must not be empty"),
+            (
+                "title: Foo\nrepo: github.com/foo",
+                "repo: \"github.com/foo\"",
+            ),
+            (
+                "title: Foo\ncolors:\n  primary: \"red; }\"",
+                "colors.primary: \"red; }\"",
+            ),
+            (
+                "title: Foo\nhighlighting:\n  theme: Monokai",
+                "highlighting.theme: \"Monokai\" is not a known theme",
+            ),
+            (
+                "title: Foo\nhighlighting:\n  mode: css",
+                "unknown variant `css`",
+            ),
+            (
+                "title: Foo\nhighlighting:\n  syntaxes: nope",
+                "highlighting.syntaxes: ",
+            ),
+            ("title: Foo\ntemplates: nope", "templates: "),
+            ("title: Foo\nstatic: nope", "static: "),
+        ];
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("jelly.yaml");
+
+        for (yaml, expected) in cases {
+            std::fs::write(&path, yaml).unwrap();
+
+            match SiteConfig::from_file(PathBuf::from("."), &path) {
+                Err(e @ (JellyError::ConfigParse(..) | JellyError::InvalidConfig(..))) => {
+                    let msg = e.to_string();
+                    assert!(msg.contains(expected), "{msg:?} lacks {expected:?}");
+                }
+                Err(e) => panic!("unexpected error: {e}"),
+                Ok(_) => panic!("config should be invalid: {yaml}"),
+            }
+        }
+    }
+
+    #[test]
+    fn colors() {
+        for color in [
+            "#fff",
+            "#123456",
+            "#12345678",
+            "teal",
+            "rgb(1, 2, 3)",
+            "hsl(1 2% 3%)",
+        ] {
+            assert!(is_valid_color(color), "{color}");
+        }
+
+        for color in ["", "#ggg", "#12345", "red;", "rgb(1, 2, 3", "url(foo)"] {
+            assert!(!is_valid_color(color), "{color}");
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use std::{
+    fs::{copy, create_dir_all, read_dir},
+    path::Path,
+};
+
+use crate::{config::SiteConfig, error::JellyError};
+
+use super::section::is_page;
+
+// Files in the content directory that configure the site rather than being part of it
+const CONFIG_FILES: [&str; 2] = ["_dir.yaml", "jelly.yaml"];
+
+// Copies everything in the content directory that isn't a page or a config file into the output
+// directory, keeping the same layout, followed by the contents of the static directory (if any).
+pub(crate) fn copy_assets(config: &SiteConfig, out: &Path) -> Result<(), JellyError> {
+    copy_dir(&config.root, out, &|path| is_content_asset(path, config))?;
+
+    if config.static_dir.is_dir() {
+        copy_dir(&config.static_dir, out, &|path| !is_hidden(path))?;
+    }
+
+    Ok(())
+}
+
+fn copy_dir(dir: &Path, out: &Path, include: &dyn Fn(&Path) -> bool) -> Result<(), JellyError> {
+    for entry in read_dir(dir)? {
+        let path = entry?.path();
+
+        if !include(&path) {
+            continue;
+        }
+
+        let target = out.join(path.strip_prefix(dir)?);
+
+        if path.is_dir() {
+            copy_dir(&path, &target, include)?;
+        } else {
+            create_dir_all(out)?;
+            copy(&path, &target)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn is_content_asset(path: &Path, config: &SiteConfig) -> bool {
+    if is_hidden(path) {
+        return false;
+    }
+
+    if path.is_dir() {
+        return !config.is_reserved_dir(path);
+    }
+
+    let is_config = path
+        .file_name()
+        .is_some_and(|name| CONFIG_FILES.iter().any(|f| name == *f));
+
+    !is_page(path) && !is_config
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+}
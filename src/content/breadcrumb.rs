@@ -1,17 +1,16 @@
-use std::path::PathBuf;
-
 use serde::Serialize;
 
 #[derive(Clone, Debug, Eq, PartialEq, Serialize)]
 pub(crate) struct Link {
-    pub(crate) path: PathBuf,
+    // Sections without an index page have nothing to link to
+    pub(crate) url: Option<String>,
     pub(crate) title: String,
 }
 
 impl Link {
-    pub(crate) fn new(path: &PathBuf, title: &str) -> Self {
+    pub(crate) fn new(url: Option<&str>, title: &str) -> Self {
         Self {
-            path: PathBuf::from(path),
+            url: url.map(String::from),
             title: String::from(title),
         }
     }
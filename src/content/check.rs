@@ -0,0 +1,167 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    config::SiteConfig,
+    md::{Reference, ReferenceKind},
+    utils::is_relative_url,
+};
+
+use super::{page::Page, Site};
+
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) struct Problem {
+    pub(crate) file: String,
+    pub(crate) line: Option<usize>,
+    pub(crate) message: String,
+}
+
+impl Site {
+    // Finds internal links that don't lead to a page or file, fragments that don't match any
+    // element on the target page, and images that don't exist. External URLs aren't checked.
+    pub(crate) fn check(&self, config: &SiteConfig) -> Vec<Problem> {
+        let pages = self.pages();
+
+        let anchors: HashMap<&str, HashSet<&str>> = pages
+            .iter()
+            .map(|page| {
+                (
+                    page.url.as_str(),
+                    page.anchors.iter().map(String::as_str).collect(),
+                )
+            })
+            .collect();
+
+        let mut problems: Vec<Problem> = Vec::new();
+
+        for page in &pages {
+            for reference in &page.references {
+                if let Some(message) = check_reference(page, reference, &anchors, config) {
+                    problems.push(Problem {
+                        file: page.path.clone(),
+                        line: reference.line,
+                        message,
+                    });
+                }
+            }
+        }
+
+        problems.sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+        problems
+    }
+}
+
+fn check_reference(
+    page: &Page,
+    reference: &Reference,
+    anchors: &HashMap<&str, HashSet<&str>>,
+    config: &SiteConfig,
+) -> Option<String> {
+    let url = reference.url.as_str();
+
+    // Relative URLs that are still around at this point couldn't be resolved when the page was built
+    if is_relative_url(url) {
+        return Some(match reference.kind {
+            ReferenceKind::Link => format!("link to {url} doesn't lead to a page"),
+            ReferenceKind::Image => format!("image {url} doesn't exist"),
+        });
+    }
+
+    if !url.starts_with(['/', '#']) {
+        return None;
+    }
+
+    let fragment_start = url.find('#').unwrap_or(url.len());
+    let (path, fragment) = url.split_at(fragment_start);
+    let path = path.split('?').next().unwrap_or_default();
+
+    let target = if path.is_empty() {
+        page.url.as_str()
+    } else if path == "/" {
+        path
+    } else {
+        path.trim_end_matches('/')
+    };
+
+    match (reference.kind, anchors.get(target)) {
+        (ReferenceKind::Link, Some(ids)) => {
+            let fragment = fragment.trim_start_matches('#');
+
+            if !fragment.is_empty() && !ids.contains(fragment) {
+                Some(format!("no heading with id {fragment:?} on {target}"))
+            } else {
+                None
+            }
+        }
+        _ if is_file(target, config) => None,
+        (ReferenceKind::Link, None) => Some(format!("link to {url} doesn't lead to a page")),
+        (ReferenceKind::Image, _) => Some(format!("image {url} doesn't exist")),
+    }
+}
+
+// Files in the content or static directory end up in the output at the same path
+fn is_file(url_path: &str, config: &SiteConfig) -> bool {
+    let relative = url_path.trim_start_matches('/');
+
+    !relative.is_empty()
+        && (config.root.join(relative).is_file() || config.static_dir.join(relative).is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{config::SiteConfig, content::Site};
+
+    use super::Problem;
+
+    #[test]
+    fn check_links() {
+        let config = SiteConfig::new(PathBuf::from("tests/full/broken"));
+        let problems = Site::build(&config).unwrap().check(&config);
+
+        let expected = vec![
+            (
+                "tests/full/broken/guide.md",
+                5,
+                "link to /does/not/exist doesn't lead to a page",
+            ),
+            (
+                "tests/full/broken/guide.md",
+                17,
+                "no heading with id \"anywhere\" on /guide",
+            ),
+            (
+                "tests/full/broken/index.md",
+                7,
+                "link to nope.md doesn't lead to a page",
+            ),
+            (
+                "tests/full/broken/index.md",
+                9,
+                "no heading with id \"uninstall\" on /guide",
+            ),
+            (
+                "tests/full/broken/index.md",
+                11,
+                "image /missing.png doesn't exist",
+            ),
+            (
+                "tests/full/broken/index.md",
+                13,
+                "no heading with id \"nowhere\" on /",
+            ),
+        ];
+
+        assert_eq!(
+            problems,
+            expected
+                .into_iter()
+                .map(|(file, line, message)| Problem {
+                    file: String::from(file),
+                    line: Some(line),
+                    message: String::from(message),
+                })
+                .collect::<Vec<Problem>>()
+        );
+    }
+}
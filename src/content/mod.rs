@@ -1,4 +1,6 @@
+mod assets;
 mod breadcrumb;
+mod check;
 mod front;
 mod page;
 mod section;
@@ -7,7 +9,8 @@ mod sort;
 mod title;
 
 pub(super) use breadcrumb::Link;
+pub(super) use check::Problem;
 pub(super) use page::Page;
 pub(super) use section::Section;
-pub(super) use site::{Site, SiteAttrs};
-pub(super) use sort::by_title;
+pub(super) use site::{PageSiteAttrs, Site, SiteAttrs};
+pub(super) use sort::{by_title, pages_by_order, sections_by_order};
@@ -1,22 +1,28 @@
 use crate::{
     config::SiteConfig,
     error::JellyError,
-    md::{ast, build_search_index_for_page, render, SearchIndex, TableOfContents},
-    utils::get_file,
+    md::{
+        anchor_ids, assign_callout_ids, ast_with_config, build_search_index_for_page,
+        collect_references, duplicate_heading_ids, has_diagrams, include_code, math_errors, render,
+        resolve_image_urls, resolve_links, unknown_languages, Highlighter, Reference, SearchIndex,
+        TableOfContents,
+    },
+    utils::{get_file, join_url},
 };
 
 use super::{
     breadcrumb::Link,
     front::FrontMatter,
+    section::has_index_page,
     title::{infer_page_title, WithTitle},
 };
 use gray_matter::{engine::YAML, Matter};
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use serde::Serialize;
 use std::{
     cmp::Ordering,
     path::{Path, PathBuf},
 };
+use tracing::warn;
 
 #[derive(Clone, Debug, Eq, PartialEq, Serialize)]
 pub(crate) struct Page {
@@ -30,12 +36,33 @@ pub(crate) struct Page {
     pub(crate) table_of_contents: TableOfContents,
     pub(crate) search_index: SearchIndex,
     pub(crate) order: Option<usize>,
+    // Links and images with their lines in the Markdown file
+    pub(crate) references: Vec<Reference>,
+    // Files that code blocks are included from
+    pub(crate) includes: Vec<PathBuf>,
+    // The ids of the elements on the page, which links to it can point at
+    pub(crate) anchors: Vec<String>,
+    // Whether the page has Mermaid diagrams to render
+    pub(crate) has_diagrams: bool,
+    // Problems that don't stop the page from being built, like unresolved links
+    pub(crate) warnings: Vec<String>,
 }
 
 #[derive(Clone, Serialize)]
 pub(super) struct PageEntry {
     pub(super) title: String,
     pub(super) url: String,
+    pub(super) order: Option<usize>,
+}
+
+impl From<&Page> for PageEntry {
+    fn from(page: &Page) -> Self {
+        Self {
+            title: page.title.clone(),
+            url: page.url.clone(),
+            order: page.order,
+        }
+    }
 }
 
 impl Page {
@@ -43,6 +70,7 @@ impl Page {
         path: &Path,
         breadcrumb: &[(&PathBuf, &str)],
         config: &SiteConfig,
+        highlighter: &Highlighter,
     ) -> Result<Self, JellyError> {
         let file: String = get_file(path)?;
         let matter = Matter::<YAML>::new();
@@ -50,6 +78,9 @@ impl Page {
         let front = FrontMatter::parse(result.data)?;
         let order = front.order;
 
+        // Line numbers in the content are offset by the front matter
+        let front_matter_lines = file_lines_before(&file, &result.content);
+
         if let Some(order) = order {
             if order == 0 {
                 return Err(JellyError::ZeroOrder(path.to_path_buf()));
@@ -58,54 +89,118 @@ impl Page {
 
         let title: String = infer_page_title(front, path, file, &config.title_config);
         let relative_path = path.strip_prefix(&config.root)?;
-        let tree = ast(&result.content);
+        let mut tree = ast_with_config(&result.content, &config.markdown, highlighter);
+        let includes = include_code(&mut tree, path)?;
         let table_of_contents = TableOfContents::parse(&tree);
+
+        let url = page_url(relative_path);
+        let dir_url = url_from_path(relative_path.parent().unwrap_or(Path::new("")));
+
+        resolve_image_urls(&mut tree, &dir_url);
+        assign_callout_ids(&mut tree, &url);
+
+        let unresolved = resolve_links(&mut tree, &result.content, |href| {
+            resolve_link(href, &dir_url, &config.root)
+        });
+
+        let mut warnings: Vec<String> = Vec::new();
+
+        for link in unresolved {
+            let line = link.line.map(|l| l + front_matter_lines).unwrap_or(0);
+            warnings.push(format!(
+                "{}:{line}: unresolved link to {}",
+                path.display(),
+                link.url
+            ));
+        }
+
+        for code in unknown_languages(&tree, &result.content) {
+            let line = code.line.map(|l| l + front_matter_lines).unwrap_or(0);
+            warnings.push(format!(
+                "{}:{line}: no syntax for code block language {}, so it isn't highlighted",
+                path.display(),
+                code.language
+            ));
+        }
+
+        for heading in duplicate_heading_ids(&tree, &result.content) {
+            let line = heading.line.map(|l| l + front_matter_lines).unwrap_or(0);
+            warnings.push(format!(
+                "{}:{line}: heading id {} is already used on the page",
+                path.display(),
+                heading.id
+            ));
+        }
+
+        for math in math_errors(&tree, &result.content) {
+            let line = math.line.map(|l| l + front_matter_lines).unwrap_or(0);
+            warnings.push(format!(
+                "{}:{line}: {} in math {}",
+                path.display(),
+                math.message,
+                math.tex
+            ));
+        }
+
+        for warning in &warnings {
+            warn!("{warning}");
+        }
+
+        let references: Vec<Reference> = collect_references(&tree, &result.content)
+            .into_iter()
+            .map(|r| Reference {
+                line: r.line.map(|l| l + front_matter_lines),
+                ..r
+            })
+            .collect();
+
         let html = render(&tree);
-        let search_index = build_search_index_for_page(&title, &tree);
-
-        let url = if let Some(last_segment) = relative_path
-            .with_extension("")
-            .file_name()
-            .and_then(|name| name.to_str())
-        {
-            if last_segment == "index" {
-                relative_path
-                    .parent()
-                    .unwrap_or(relative_path)
-                    .to_path_buf()
-            } else {
-                relative_path.to_path_buf()
-            }
-        } else {
-            relative_path.to_path_buf()
-        };
+        let anchors = anchor_ids(&tree);
+        let has_diagrams = has_diagrams(&tree);
+
+        let trail: Vec<String> = breadcrumb
+            .iter()
+            .map(|(_, title)| String::from(*title))
+            .collect();
+        let search_index = build_search_index_for_page(&title, &url, &trail, &tree);
 
         Ok(Page {
             path: String::from(path.to_string_lossy()),
             relative_path: String::from(relative_path.to_string_lossy()),
-            url: String::from(url.to_string_lossy()),
+            url,
             title,
             body: result.content,
             html,
             breadcrumb: breadcrumb
-                .par_iter()
-                .copied()
-                .map(|(a, b)| Link::new(a, b))
-                .collect(),
+                .iter()
+                .map(|(dir, title)| breadcrumb_link(dir, title, &config.root))
+                .collect::<Result<Vec<Link>, JellyError>>()?,
             table_of_contents,
             search_index,
             order,
+            references,
+            includes,
+            anchors,
+            has_diagrams,
+            warnings,
         })
     }
 
+    // Every page is an index.html in a directory named after its URL, so that the URL works on
+    // static hosts that don't know to add .html
     pub(crate) fn html_path(&self, root: PathBuf) -> PathBuf {
-        root.join(&self.relative_path)
+        root.join(self.url.trim_start_matches('/'))
+            .join("index.html")
     }
 
     fn is_index(&self) -> bool {
         self.relative_path == "index.md"
     }
 
+    pub(super) fn is_section_index(&self) -> bool {
+        Path::new(&self.relative_path).file_stem() == Some("index".as_ref())
+    }
+
     #[allow(clippy::too_many_arguments)]
     #[cfg(test)]
     pub(crate) fn new(
@@ -131,6 +226,11 @@ impl Page {
             table_of_contents,
             search_index,
             order,
+            references: Vec::new(),
+            includes: Vec::new(),
+            anchors: Vec::new(),
+            has_diagrams: false,
+            warnings: Vec::new(),
         }
     }
 }
@@ -163,3 +263,66 @@ impl WithTitle for Page {
         self.title.to_owned()
     }
 }
+
+// index.md files are served at their directory's URL and other pages drop the .md extension
+fn breadcrumb_link(dir: &Path, title: &str, root: &Path) -> Result<Link, JellyError> {
+    let url = if has_index_page(dir)? {
+        Some(url_from_path(dir.strip_prefix(root)?))
+    } else {
+        None
+    };
+
+    Ok(Link::new(url.as_deref(), title))
+}
+
+fn page_url(relative_path: &Path) -> String {
+    let url = if relative_path.file_stem() == Some("index".as_ref()) {
+        relative_path
+            .parent()
+            .unwrap_or(relative_path)
+            .to_path_buf()
+    } else {
+        relative_path.with_extension("")
+    };
+
+    url_from_path(&url)
+}
+
+// Links to other pages point at their Markdown files (so that they work on GitHub and in
+// editors) and need to be mapped to page URLs. Other relative links are only made absolute.
+fn resolve_link(href: &str, dir_url: &str, root: &Path) -> Option<String> {
+    let suffix_start = href.find(['?', '#']).unwrap_or(href.len());
+    let (path, suffix) = href.split_at(suffix_start);
+
+    if !path.ends_with(".md") {
+        return Some(join_url(dir_url, href));
+    }
+
+    let target = join_url(dir_url, path);
+    let relative_path = Path::new(target.trim_start_matches('/'));
+
+    if root.join(relative_path).is_file() {
+        Some(format!("{}{suffix}", page_url(relative_path)))
+    } else {
+        None
+    }
+}
+
+// The content returned by gray_matter isn't always an exact suffix of the file (trailing
+// whitespace gets normalized) so look for where it starts instead.
+fn file_lines_before(file: &str, content: &str) -> usize {
+    match file.find(content.trim_end()) {
+        Some(start) => file[..start].matches('\n').count(),
+        None => 0,
+    }
+}
+
+// Site URLs are absolute and always use forward slashes, whatever the platform.
+pub(super) fn url_from_path(relative_path: &Path) -> String {
+    let segments: Vec<String> = relative_path
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+
+    format!("/{}", segments.join("/"))
+}
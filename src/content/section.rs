@@ -1,19 +1,21 @@
 use crate::config::SiteConfig;
 use crate::error::JellyError;
+use crate::md::Highlighter;
 use crate::utils::vec_or_none;
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use serde::Serialize;
 use std::fs::{metadata, read_dir};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use super::by_title;
-use super::page::{Page, PageEntry};
+use super::page::{url_from_path, Page, PageEntry};
 use super::title::{get_section_config, WithTitle};
+use super::{by_title, pages_by_order, sections_by_order};
 
 #[derive(Clone, Debug, PartialEq, Serialize)]
 pub(crate) struct Section {
     pub(super) title: String,
     pub(super) url: String,
+    pub(super) order: Option<usize>,
     pub(super) pages: Option<Vec<Page>>,
     pub(super) sections: Option<Vec<Section>>,
 }
@@ -22,33 +24,91 @@ pub(crate) struct Section {
 pub(super) struct SectionEntry {
     pub(super) title: String,
     pub(super) url: String,
+    pub(super) order: Option<usize>,
+    // Serialized through ActiveSectionEntry, which marks the page being rendered
+    #[serde(skip)]
     pub(super) pages: Option<Vec<PageEntry>>,
+    #[serde(skip)]
     pub(super) sections: Option<Vec<SectionEntry>>,
+    // Sections without an index page aren't links
+    pub(super) has_index: bool,
 }
 
-impl From<Section> for SectionEntry {
-    fn from(s: Section) -> Self {
+// The navigation tree as seen from the page at `url`: the page is marked active and every section
+// on the path to it is expanded. The flags are worked out while serializing so that every page
+// shares one tree.
+#[derive(Serialize)]
+pub(super) struct ActiveSectionEntry<'a> {
+    #[serde(flatten)]
+    section: &'a SectionEntry,
+    pages: Option<Vec<ActivePageEntry<'a>>>,
+    sections: Option<Vec<ActiveSectionEntry<'a>>>,
+    // The section's index page is the one being rendered
+    active: bool,
+    // The page being rendered is somewhere inside this section
+    expanded: bool,
+}
+
+#[derive(Serialize)]
+pub(super) struct ActivePageEntry<'a> {
+    #[serde(flatten)]
+    page: &'a PageEntry,
+    active: bool,
+}
+
+impl From<&Section> for SectionEntry {
+    fn from(s: &Section) -> Self {
         Self {
             title: s.title.clone(),
             url: s.url.clone(),
-            pages: s.pages.as_ref().map(|ps| {
-                ps.par_iter()
-                    .map(|Page { title, url, .. }| PageEntry {
-                        title: title.to_string(),
-                        url: url.to_string(),
-                    })
-                    .collect()
-            }),
-            sections: s.sections.as_ref().map(|ss| {
-                ss.par_iter()
-                    .map(|s| SectionEntry {
-                        title: s.title.clone(),
-                        url: s.url.clone(),
-                        pages: None,
-                        sections: None,
-                    })
-                    .collect()
+            order: s.order,
+            // A section's index page is reached through the section itself
+            pages: s.pages.as_ref().and_then(|ps| {
+                vec_or_none(
+                    ps.par_iter()
+                        .filter(|p| !p.is_section_index())
+                        .map(PageEntry::from)
+                        .collect(),
+                )
             }),
+            sections: s
+                .sections
+                .as_ref()
+                .map(|ss| ss.par_iter().map(SectionEntry::from).collect()),
+            has_index: s
+                .pages
+                .as_ref()
+                .is_some_and(|ps| ps.iter().any(Page::is_section_index)),
+        }
+    }
+}
+
+impl SectionEntry {
+    pub(super) fn for_page<'a>(&'a self, url: &str) -> ActiveSectionEntry<'a> {
+        let pages: Option<Vec<ActivePageEntry>> = self.pages.as_ref().map(|ps| {
+            ps.iter()
+                .map(|page| ActivePageEntry {
+                    page,
+                    active: page.url == url,
+                })
+                .collect()
+        });
+        let sections: Option<Vec<ActiveSectionEntry>> = self
+            .sections
+            .as_ref()
+            .map(|ss| ss.iter().map(|s| s.for_page(url)).collect());
+
+        let active = self.url == url;
+        let expanded = active
+            || pages.iter().flatten().any(|p| p.active)
+            || sections.iter().flatten().any(|s| s.expanded);
+
+        ActiveSectionEntry {
+            section: self,
+            pages,
+            sections,
+            active,
+            expanded,
         }
     }
 }
@@ -82,6 +142,7 @@ impl Section {
         path: &PathBuf,
         breadcrumb: Option<&Vec<(&PathBuf, &str)>>,
         config: &SiteConfig,
+        highlighter: &Highlighter,
     ) -> Result<Self, JellyError> {
         let section_config = &get_section_config(path, config)?;
 
@@ -95,7 +156,9 @@ impl Section {
 
         breadcrumb_acc.push((path, &section_config.title));
 
-        let pages: Vec<Page> = get_pages_in_dir(path, &breadcrumb_acc, config)?;
+        let mut pages: Vec<Page> = get_pages_in_dir(path, &breadcrumb_acc, config, highlighter)?;
+        pages.sort_by(pages_by_order);
+
         let mut sections: Vec<Section> = Vec::new();
 
         for entry in read_dir(path)? {
@@ -103,15 +166,19 @@ impl Section {
             let path = entry.path();
             let meta = metadata(&path)?;
 
-            if meta.is_dir() {
-                let section = Self::from_path(&path, Some(&breadcrumb_acc), config)?;
+            // Directories holding nothing but assets (images and such) aren't sections
+            if meta.is_dir() && !config.is_reserved_dir(&path) && contains_pages(&path)? {
+                let section = Self::from_path(&path, Some(&breadcrumb_acc), config, highlighter)?;
                 sections.push(section);
             }
         }
 
+        sections.sort_by(sections_by_order);
+
         Ok(Section {
             title: section_config.title.clone(),
-            url: path.display().to_string(),
+            url: url_from_path(path.strip_prefix(&config.root)?),
+            order: section_config.order,
             pages: vec_or_none(pages),
             sections: vec_or_none(sections),
         })
@@ -127,6 +194,7 @@ impl Section {
         Self {
             title: String::from(title),
             url: String::from(url),
+            order: None,
             pages,
             sections,
         }
@@ -139,10 +207,41 @@ impl WithTitle for Section {
     }
 }
 
+pub(super) fn is_page(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.to_string_lossy().ends_with("md"))
+}
+
+// Whether the section in `dir` has a page of its own to link to
+pub(super) fn has_index_page(dir: &Path) -> Result<bool, JellyError> {
+    for entry in read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_file() && is_page(&path) && path.file_stem() == Some("index".as_ref()) {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+fn contains_pages(dir: &Path) -> Result<bool, JellyError> {
+    for entry in read_dir(dir)? {
+        let path = entry?.path();
+
+        if (path.is_file() && is_page(&path)) || (path.is_dir() && contains_pages(&path)?) {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
 fn get_pages_in_dir(
     dir: &PathBuf,
     breadcrumb: &[(&PathBuf, &str)],
     config: &SiteConfig,
+    highlighter: &Highlighter,
 ) -> Result<Vec<Page>, JellyError> {
     let mut pages: Vec<Page> = Vec::new();
 
@@ -150,13 +249,9 @@ fn get_pages_in_dir(
         let entry = entry?;
         let path = entry.path();
         let meta = metadata(&path)?;
-        if meta.is_file() {
-            if let Some(ext) = path.extension() {
-                if ext.to_string_lossy().ends_with("md") {
-                    let page = Page::from_path(&path, breadcrumb, config)?;
-                    pages.push(page);
-                }
-            }
+        if meta.is_file() && is_page(&path) {
+            let page = Page::from_path(&path, breadcrumb, config, highlighter)?;
+            pages.push(page);
         }
     }
 
@@ -166,3 +261,88 @@ fn get_pages_in_dir(
 
     Ok(pages)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{config::SiteConfig, error::JellyError, md::Highlighter};
+
+    use super::{Section, SectionEntry};
+
+    #[test]
+    fn section_ordering() {
+        let config = SiteConfig::new(PathBuf::from("tests/full/ordered"));
+        let section =
+            Section::from_path(&config.root, None, &config, &Highlighter::default()).unwrap();
+
+        let titles: Vec<(&str, Option<usize>)> = section
+            .sections
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|s| (s.title.as_str(), s.order))
+            .collect();
+
+        assert_eq!(
+            titles,
+            vec![
+                ("Zeta", Some(1)),
+                ("Beta", Some(2)),
+                ("Alpha", None),
+                ("Gamma", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn zero_section_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let sub = dir.path().join("sub");
+        std::fs::create_dir(&sub).unwrap();
+        std::fs::write(dir.path().join("index.md"), "# Root").unwrap();
+        std::fs::write(sub.join("index.md"), "# Sub").unwrap();
+        std::fs::write(sub.join("_dir.yaml"), "order: 0").unwrap();
+
+        let config = SiteConfig::new(dir.path().to_path_buf());
+
+        assert!(matches!(
+            Section::from_path(&config.root, None, &config, &Highlighter::default()),
+            Err(JellyError::ZeroSectionOrder(_))
+        ));
+    }
+
+    #[test]
+    fn navigation_tree() {
+        let config = SiteConfig::new(PathBuf::from("tests/full/ordered"));
+        let section =
+            Section::from_path(&config.root, None, &config, &Highlighter::default()).unwrap();
+        let tree = SectionEntry::from(&section);
+
+        let nav = tree.for_page("/beta/intro");
+        assert!(nav.expanded);
+        assert!(!nav.active);
+        assert!(nav.pages.is_none());
+
+        let sections = nav.sections.as_ref().unwrap();
+        let beta = &sections[1];
+        assert_eq!(beta.section.url, "/beta");
+        assert!(beta.expanded);
+        assert!(!beta.active);
+
+        let intro = &beta.pages.as_ref().unwrap()[0];
+        assert_eq!(intro.page.url, "/beta/intro");
+        assert!(intro.active);
+
+        for other in [&sections[0], &sections[2], &sections[3]] {
+            assert!(!other.expanded);
+            assert!(!other.active);
+            assert!(other.pages.is_none());
+        }
+
+        let nav = tree.for_page("/zeta");
+        let sections = nav.sections.as_ref().unwrap();
+        assert!(sections[0].active && sections[0].expanded);
+        assert!(!sections[1].expanded);
+    }
+}
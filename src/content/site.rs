@@ -1,16 +1,25 @@
 use std::{fs::create_dir_all, path::PathBuf};
 
-use ammonia::clean;
+use ammonia::Builder;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use serde::Serialize;
 
 use crate::{
-    config::SiteConfig,
+    config::{ColorsConfig, HighlightMode, SearchProvider, SiteConfig},
     error::JellyError,
-    md::{render_page, SearchDocument},
+    md::{highlighting_css, Highlighter, Renderer, SearchDocument},
     utils::write_file,
 };
 
-use super::{page::Page, section::SectionEntry, Section};
+use super::{
+    assets::copy_assets,
+    page::Page,
+    section::{ActiveSectionEntry, SectionEntry},
+    Section,
+};
+
+const SEARCH_INDEX_FILE: &str = "search-index.json";
+const HIGHLIGHTING_CSS_FILE: &str = "highlighting.css";
 
 #[derive(Clone, Debug, PartialEq, Serialize)]
 pub(crate) struct Site(pub(crate) Section);
@@ -18,9 +27,48 @@ pub(crate) struct Site(pub(crate) Section);
 #[derive(Clone, Serialize)]
 pub(crate) struct SiteAttrs {
     title: String,
+    description: Option<String>,
+    repo: Option<String>,
+    colors: ColorsConfig,
+    search: SearchProvider,
+    search_index_url: String,
+    // Not set when code is highlighted with inline styles
+    highlighting_css_url: Option<String>,
+    #[serde(skip)]
     root: SectionEntry,
 }
 
+// What templates get as `site` when rendering the page at a given URL
+#[derive(Serialize)]
+pub(crate) struct PageSiteAttrs<'a> {
+    #[serde(flatten)]
+    site: &'a SiteAttrs,
+    root: ActiveSectionEntry<'a>,
+}
+
+impl SiteAttrs {
+    // The navigation tree with the page (and the sections containing it) marked as active
+    pub(crate) fn for_page<'a>(&'a self, url: &str) -> PageSiteAttrs<'a> {
+        PageSiteAttrs {
+            site: self,
+            root: self.root.for_page(url),
+        }
+    }
+}
+
+// ammonia's defaults, plus the heading ids and heading anchor classes that links to sections of a
+// page rely on
+fn sanitizer() -> Builder<'static> {
+    let mut builder = Builder::default();
+
+    for heading in ["h1", "h2", "h3", "h4", "h5", "h6"] {
+        builder.add_tag_attributes(heading, &["id"]);
+    }
+    builder.add_tag_attributes("a", &["class"]);
+
+    builder
+}
+
 #[derive(Serialize)]
 pub(crate) struct SiteIndex(Vec<SearchDocument>);
 
@@ -29,29 +77,75 @@ impl Site {
         config: &SiteConfig,
         out: PathBuf,
         sanitize: bool,
-    ) -> Result<(), JellyError> {
+    ) -> Result<Self, JellyError> {
+        Self::write_with_renderer(config, &Renderer::new(config)?, out, sanitize)
+    }
+
+    // Use this instead of write when the renderer needs customizing (custom helpers, for example)
+    pub(crate) fn write_with_renderer(
+        config: &SiteConfig,
+        renderer: &Renderer,
+        out: PathBuf,
+        sanitize: bool,
+    ) -> Result<Self, JellyError> {
         let this: Self = Self::build(config)?;
+        this.write_to(config, renderer, out, sanitize)?;
+
+        Ok(this)
+    }
+
+    // Writes a site that's already been built, so that it can be checked before there's any output
+    pub(crate) fn write_to(
+        &self,
+        config: &SiteConfig,
+        renderer: &Renderer,
+        out: PathBuf,
+        sanitize: bool,
+    ) -> Result<(), JellyError> {
+        let attrs = self.attrs(config);
+        let sanitizer = sanitizer();
 
-        for page in this.pages() {
-            let html = render_page(page, &this.attrs())?;
-            let mut path = page.html_path(out.clone());
+        copy_assets(config, &out)?;
+
+        self.pages().par_iter().try_for_each(|page| {
+            let html = renderer.render_page(page, &attrs)?;
+            let path = page.html_path(out.clone());
 
             if let Some(dir) = path.as_path().parent() {
                 create_dir_all(dir)?;
             }
 
-            let final_html = if sanitize { clean(&html) } else { html };
+            let final_html = if sanitize {
+                sanitizer.clean(&html).to_string()
+            } else {
+                html
+            };
+
+            write_file(&path, final_html)
+        })?;
 
-            path.set_extension("html");
+        create_dir_all(&out)?;
+        write_file(
+            &out.join(SEARCH_INDEX_FILE),
+            serde_json::to_string(&self.index())?,
+        )?;
 
-            write_file(&path, final_html)?;
+        if let Some(css) = highlighting_css(&config.highlighting)? {
+            write_file(&out.join(HIGHLIGHTING_CSS_FILE), css)?;
         }
 
         Ok(())
     }
 
     pub(crate) fn build(config: &SiteConfig) -> Result<Self, JellyError> {
-        Ok(Self(Section::from_path(&config.root, None, config)?))
+        let highlighter = Highlighter::from_config(config)?;
+
+        Ok(Self(Section::from_path(
+            &config.root,
+            None,
+            config,
+            &highlighter,
+        )?))
     }
 
     pub(crate) fn index(&self) -> SiteIndex {
@@ -62,6 +156,26 @@ impl Site {
         self.0.pages()
     }
 
+    // Files outside the content that pages include code from
+    pub(crate) fn included_files(&self) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = self
+            .pages()
+            .into_iter()
+            .flat_map(|page| page.includes.iter().cloned())
+            .collect();
+
+        files.sort();
+        files.dedup();
+        files
+    }
+
+    pub(crate) fn warnings(&self) -> Vec<&String> {
+        self.pages()
+            .into_iter()
+            .flat_map(|page| page.warnings.iter())
+            .collect()
+    }
+
     pub(crate) fn documents(&self) -> Vec<SearchDocument> {
         let mut docs: Vec<SearchDocument> = Vec::new();
 
@@ -75,10 +189,17 @@ impl Site {
         docs
     }
 
-    pub(crate) fn attrs(&self) -> SiteAttrs {
+    pub(crate) fn attrs(&self, config: &SiteConfig) -> SiteAttrs {
         SiteAttrs {
-            title: self.0.title.clone(),
-            root: self.0.clone().into(),
+            title: config.title.clone().unwrap_or_else(|| self.0.title.clone()),
+            description: config.description.clone(),
+            repo: config.repo.clone(),
+            colors: config.colors.clone(),
+            search: config.search,
+            search_index_url: format!("/{SEARCH_INDEX_FILE}"),
+            highlighting_css_url: (config.highlighting.mode == HighlightMode::Classes)
+                .then(|| format!("/{HIGHLIGHTING_CSS_FILE}")),
+            root: (&self.0).into(),
         }
     }
 }
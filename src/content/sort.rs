@@ -1,7 +1,33 @@
 use std::cmp::Ordering;
 
-use super::title::WithTitle;
+use super::{title::WithTitle, Page, Section};
 
 pub(crate) fn by_title<T: WithTitle>(a: &&T, b: &&T) -> Ordering {
     a.title().cmp(&b.title()).reverse()
 }
+
+// Sections with an explicit order come first. Everything else falls back to the title (and then
+// the URL) so that the result doesn't depend on the order in which the filesystem lists directories.
+pub(crate) fn sections_by_order(a: &Section, b: &Section) -> Ordering {
+    by_order(a.order, b.order)
+        .then_with(|| a.title.cmp(&b.title))
+        .then_with(|| a.url.cmp(&b.url))
+}
+
+// Same as sections_by_order except that a section's index page always comes first.
+pub(crate) fn pages_by_order(a: &Page, b: &Page) -> Ordering {
+    b.is_section_index()
+        .cmp(&a.is_section_index())
+        .then_with(|| by_order(a.order, b.order))
+        .then_with(|| a.title.cmp(&b.title))
+        .then_with(|| a.url.cmp(&b.url))
+}
+
+fn by_order(a: Option<usize>, b: Option<usize>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
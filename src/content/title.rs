@@ -45,14 +45,18 @@ pub(super) fn get_section_config(
     config: &SiteConfig,
 ) -> Result<SectionConfigOutput, JellyError> {
     let title: String;
-    //let mut order: Option<usize> = None;
+    let mut order: Option<usize> = None;
 
     let yaml_file_path = Path::new(&path).join("_dir.yaml");
     if yaml_file_path.exists() {
         let yaml_file_str = read_to_string(&yaml_file_path)?;
         let section_config: SectionConfigInput = serde_yaml::from_str(&yaml_file_str)?;
 
-        //order = section_config.order;
+        if section_config.order == Some(0) {
+            return Err(JellyError::ZeroSectionOrder(yaml_file_path));
+        }
+
+        order = section_config.order;
 
         match section_config.title {
             Some(t) => title = t,
@@ -66,5 +70,5 @@ pub(super) fn get_section_config(
         title = t.unwrap_or_else(|| name_from_path(path, &config.title_config));
     }
 
-    Ok(SectionConfigOutput { title })
+    Ok(SectionConfigOutput { title, order })
 }
@@ -2,21 +2,42 @@ use std::{path::PathBuf, sync::mpsc::RecvError};
 
 #[derive(thiserror::Error, Debug)]
 pub enum JellyError {
+    #[error("found {0} broken link(s)")]
+    BrokenLinks(usize),
+
+    #[error("couldn't parse config file {0}: {1}")]
+    ConfigParse(PathBuf, serde_yaml::Error),
+
     #[error("ctrl-c error: {0}")]
     Ctrlc(#[from] ctrlc::Error),
 
+    #[error("templates directory {0} has both {1}.hbs and {1}.html")]
+    DuplicateTemplate(PathBuf, String),
+
     #[error("glob error: {0}")]
     Glob(#[from] glob::GlobError),
 
     #[error("highlight error: {0}")]
     Highlight(String),
 
+    #[error("invalid config file {0}: {1}: {2}")]
+    InvalidConfig(PathBuf, String, String),
+
+    #[error("{0} includes {1}, which doesn't exist")]
+    IncludeNotFound(PathBuf, PathBuf),
+
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
 
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
 
+    #[error("{0} includes region {1:?} from {2}, which doesn't have it")]
+    MissingRegion(PathBuf, String, PathBuf),
+
+    #[error("template {0} uses partial {1}, which doesn't exist")]
+    MissingPartial(String, String),
+
     #[error("no _meta.yaml found in directory: {0}")]
     NoMetaYamlFile(String),
 
@@ -54,6 +75,9 @@ pub enum JellyError {
     #[error("UTF-8 error: {0}")]
     Utf8(#[from] std::string::FromUtf8Error),
 
+    #[error("found {0} warning(s) in strict mode")]
+    Warnings(usize),
+
     #[error("WebSocket error: {0}")]
     WebSocket(#[from] Box<ws::Error>),
 
@@ -63,6 +87,9 @@ pub enum JellyError {
     #[error("order parameter on page {0} is set to zero")]
     ZeroOrder(PathBuf),
 
+    #[error("order parameter in section config {0} is set to zero")]
+    ZeroSectionOrder(PathBuf),
+
     #[error(transparent)]
     ExternalError(#[from] Box<dyn std::error::Error + Send + Sync>),
 }
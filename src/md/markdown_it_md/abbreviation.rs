@@ -0,0 +1,220 @@
+use markdown_it::{
+    parser::{
+        block::{BlockRule, BlockState},
+        core::CoreRule,
+        inline::Text,
+    },
+    plugins::cmark::{block::reference::ReferenceScanner, inline::backticks::CodeInline},
+    MarkdownIt, Node, NodeValue, Renderer,
+};
+
+// `*[HTML]: Hyper Text Markup Language` anywhere on the page marks up every HTML in its text
+#[derive(Debug)]
+struct AbbreviationDefinition {
+    label: String,
+    title: String,
+}
+
+impl NodeValue for AbbreviationDefinition {
+    fn render(&self, _: &Node, _: &mut dyn Renderer) {}
+}
+
+#[derive(Debug)]
+struct Abbreviation {
+    title: String,
+}
+
+impl NodeValue for Abbreviation {
+    fn render(&self, node: &Node, fmt: &mut dyn Renderer) {
+        fmt.open("abbr", &[("title", self.title.clone())]);
+        fmt.contents(&node.children);
+        fmt.close("abbr");
+    }
+}
+
+struct AbbreviationDefinitionScanner;
+
+impl AbbreviationDefinitionScanner {
+    fn parse(line: &str) -> Option<AbbreviationDefinition> {
+        let rest = line.strip_prefix("*[")?;
+        let (label, title) = rest.split_once("]:")?;
+        let title = title.trim();
+
+        if label.trim().is_empty() || label.contains(['[', ']']) || title.is_empty() {
+            return None;
+        }
+
+        Some(AbbreviationDefinition {
+            label: String::from(label.trim()),
+            title: String::from(title),
+        })
+    }
+}
+
+impl BlockRule for AbbreviationDefinitionScanner {
+    fn check(state: &mut BlockState) -> Option<()> {
+        if state.line_indent(state.line) >= state.md.max_indent {
+            return None;
+        }
+
+        Self::parse(state.get_line(state.line)).map(|_| ())
+    }
+
+    fn run(state: &mut BlockState) -> Option<(Node, usize)> {
+        Self::check(state)?;
+
+        let definition = Self::parse(state.get_line(state.line))?;
+
+        Some((Node::new(definition), 1))
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+// Splits text into plain text and abbreviations, which only match whole words
+fn split_text(text: &str, definitions: &[(String, String)]) -> Vec<Node> {
+    let mut nodes: Vec<Node> = Vec::new();
+    let mut plain_start = 0;
+    let mut pos = 0;
+    let mut previous: Option<char> = None;
+
+    while pos < text.len() {
+        let rest = &text[pos..];
+        let at_word_start = !previous.is_some_and(is_word_char);
+
+        let matched = definitions.iter().find(|(label, _)| {
+            at_word_start
+                && rest.starts_with(label.as_str())
+                && !rest[label.len()..].starts_with(is_word_char)
+        });
+
+        if let Some((label, title)) = matched {
+            if plain_start < pos {
+                nodes.push(Node::new(Text {
+                    content: String::from(&text[plain_start..pos]),
+                }));
+            }
+
+            let mut abbreviation = Node::new(Abbreviation {
+                title: title.clone(),
+            });
+            abbreviation.children.push(Node::new(Text {
+                content: label.clone(),
+            }));
+            nodes.push(abbreviation);
+
+            pos += label.len();
+            plain_start = pos;
+            previous = label.chars().last();
+            continue;
+        }
+
+        let c = rest.chars().next().unwrap_or_default();
+        pos += c.len_utf8();
+        previous = Some(c);
+    }
+
+    if plain_start < text.len() {
+        nodes.push(Node::new(Text {
+            content: String::from(&text[plain_start..]),
+        }));
+    }
+
+    nodes
+}
+
+fn mark_abbreviations(node: &mut Node, definitions: &[(String, String)]) {
+    // Code is left as is and abbreviations aren't marked up twice
+    if node.is::<CodeInline>() || node.is::<Abbreviation>() {
+        return;
+    }
+
+    let mut idx = 0;
+    while idx < node.children.len() {
+        let replacement = node.children[idx]
+            .cast::<Text>()
+            .map(|text| split_text(&text.content, definitions));
+
+        match replacement {
+            Some(nodes)
+                if nodes.len() > 1 || nodes.first().is_some_and(|n| n.is::<Abbreviation>()) =>
+            {
+                let len = nodes.len();
+                node.children.splice(idx..=idx, nodes);
+                idx += len;
+            }
+            _ => {
+                mark_abbreviations(&mut node.children[idx], definitions);
+                idx += 1;
+            }
+        }
+    }
+}
+
+struct AbbreviationsRule;
+
+impl CoreRule for AbbreviationsRule {
+    fn run(root: &mut Node, _: &MarkdownIt) {
+        let mut definitions: Vec<(String, String)> = Vec::new();
+
+        root.walk_mut(|node, _| {
+            node.children
+                .retain(|child| match child.cast::<AbbreviationDefinition>() {
+                    Some(definition) => {
+                        definitions.push((definition.label.clone(), definition.title.clone()));
+                        false
+                    }
+                    None => true,
+                });
+        });
+
+        if definitions.is_empty() {
+            return;
+        }
+
+        // Longer labels first so that `HTML5` wins over `HTML`
+        definitions.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()));
+
+        mark_abbreviations(root, &definitions);
+    }
+}
+
+pub(super) fn add_abbreviation_rules(md: &mut MarkdownIt) {
+    md.block
+        .add_rule::<AbbreviationDefinitionScanner>()
+        .before::<ReferenceScanner>();
+    md.add_rule::<AbbreviationsRule>();
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::tests::test_markdown_produces_expected_html;
+
+    #[test]
+    fn abbreviations() {
+        let cases: Vec<(&str, &str)> = vec![
+            (
+                indoc! {"
+                    > HTML
+
+                    HTML and HTML5 aren't XHTML or `HTML`, but [HTML](/html) is.
+                    *[HTML]: Hyper Text Markup Language
+                    *[HTML5]: The fifth version of HTML
+                "},
+                indoc! {r#"
+                    <blockquote>
+                    <p><abbr title="Hyper Text Markup Language">HTML</abbr></p>
+                    </blockquote>
+                    <p><abbr title="Hyper Text Markup Language">HTML</abbr> and <abbr title="The fifth version of HTML">HTML5</abbr> aren't XHTML or <code>HTML</code>, but <a href="/html"><abbr title="Hyper Text Markup Language">HTML</abbr></a> is.</p>
+                "#},
+            ),
+            ("*[]: Empty\n", "<p>*[]: Empty</p>\n"),
+        ];
+
+        test_markdown_produces_expected_html(cases);
+    }
+}
@@ -0,0 +1,786 @@
+use std::collections::HashMap;
+
+use markdown_it::{
+    common::utils::find_indent_of,
+    parser::block::{BlockRule, BlockState},
+    plugins::cmark::block::blockquote::BlockquoteScanner,
+    MarkdownIt, Node, NodeValue, Renderer,
+};
+use slug::slugify;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) enum AdmonitionKind {
+    Note,
+    Info,
+    Tip,
+    Success,
+    Important,
+    Warning,
+    Caution,
+    Danger,
+    Bug,
+    // Only for collapsible callouts
+    Details,
+}
+
+impl AdmonitionKind {
+    fn parse(s: &str) -> Option<Self> {
+        use AdmonitionKind::*;
+
+        let kind = match s.to_ascii_lowercase().as_str() {
+            "note" => Note,
+            "info" => Info,
+            "tip" => Tip,
+            "success" => Success,
+            "important" => Important,
+            "warning" => Warning,
+            "caution" => Caution,
+            "danger" => Danger,
+            "bug" => Bug,
+            _ => return None,
+        };
+
+        Some(kind)
+    }
+
+    fn parse_collapsible(s: &str) -> Option<Self> {
+        if s.eq_ignore_ascii_case("details") {
+            Some(AdmonitionKind::Details)
+        } else {
+            Self::parse(s)
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        use AdmonitionKind::*;
+
+        match self {
+            Note => "note",
+            Info => "info",
+            Tip => "tip",
+            Success => "success",
+            Important => "important",
+            Warning => "warning",
+            Caution => "caution",
+            Danger => "danger",
+            Bug => "bug",
+            Details => "details",
+        }
+    }
+
+    fn default_title(&self) -> String {
+        let name = self.name();
+        format!("{}{}", name[..1].to_ascii_uppercase(), &name[1..])
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct Admonition {
+    kind: AdmonitionKind,
+    pub(super) title: String,
+    collapse: Option<Collapse>,
+}
+
+// Collapsible callouts are rendered as <details> elements. The id is what the page template
+// uses to remember whether the callout was left open.
+#[derive(Debug)]
+struct Collapse {
+    open: bool,
+    id: Option<String>,
+}
+
+impl Admonition {
+    fn new(kind: AdmonitionKind, title: Option<&str>) -> Self {
+        Self {
+            kind,
+            title: title
+                .map(String::from)
+                .unwrap_or_else(|| kind.default_title()),
+            collapse: None,
+        }
+    }
+
+    fn collapsible(kind: AdmonitionKind, title: Option<&str>, open: bool) -> Self {
+        Self {
+            collapse: Some(Collapse { open, id: None }),
+            ..Self::new(kind, title)
+        }
+    }
+
+    fn render_collapsible(&self, collapse: &Collapse, node: &Node, fmt: &mut dyn Renderer) {
+        let mut attrs = vec![(
+            "class",
+            format!("admonition admonition-{}", self.kind.name()),
+        )];
+        if let Some(id) = &collapse.id {
+            attrs.push(("id", id.clone()));
+        }
+        if collapse.open {
+            attrs.push(("open", String::new()));
+        }
+
+        fmt.cr();
+        fmt.open("details", &attrs);
+        fmt.cr();
+        fmt.open("summary", &[("class", String::from("admonition-title"))]);
+        fmt.text(&self.title);
+        fmt.close("summary");
+        fmt.cr();
+        fmt.contents(&node.children);
+        fmt.cr();
+        fmt.close("details");
+        fmt.cr();
+    }
+}
+
+// Ids are derived from the page URL and the callout title so that they stay the same from one
+// build to the next. Callouts with the same title on a page are numbered.
+pub(crate) fn assign_callout_ids(root: &mut Node, page_url: &str) {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+
+    root.walk_mut(|node, _| {
+        if let Some(admonition) = node.cast_mut::<Admonition>() {
+            if let Some(collapse) = &mut admonition.collapse {
+                let base = format!(
+                    "callout-{}",
+                    slugify(format!("{page_url} {}", admonition.title))
+                );
+                let count = seen.entry(base.clone()).or_insert(0);
+                *count += 1;
+
+                collapse.id = Some(if *count == 1 {
+                    base
+                } else {
+                    format!("{base}-{count}")
+                });
+            }
+        }
+    });
+}
+
+impl NodeValue for Admonition {
+    fn render(&self, node: &Node, fmt: &mut dyn Renderer) {
+        if let Some(collapse) = &self.collapse {
+            return self.render_collapsible(collapse, node, fmt);
+        }
+
+        let attrs = vec![
+            (
+                "class",
+                format!("admonition admonition-{}", self.kind.name()),
+            ),
+            ("role", String::from("note")),
+        ];
+
+        fmt.cr();
+        fmt.open("div", &attrs);
+        fmt.cr();
+        fmt.open("p", &[("class", String::from("admonition-title"))]);
+        fmt.text(&self.title);
+        fmt.close("p");
+        fmt.cr();
+        fmt.contents(&node.children);
+        fmt.cr();
+        fmt.close("div");
+        fmt.cr();
+    }
+}
+
+// GitHub-style alerts: a blockquote whose first line is only a marker like `> [!NOTE]`
+struct AlertScanner;
+
+impl AlertScanner {
+    fn marker(line: &str) -> Option<AdmonitionKind> {
+        let marker = line.strip_prefix('>')?.trim();
+        AdmonitionKind::parse(marker.strip_prefix("[!")?.strip_suffix(']')?)
+    }
+}
+
+impl BlockRule for AlertScanner {
+    fn check(state: &mut BlockState) -> Option<()> {
+        if state.line_indent(state.line) >= state.md.max_indent {
+            return None;
+        }
+
+        Self::marker(state.get_line(state.line)).map(|_| ())
+    }
+
+    fn run(state: &mut BlockState) -> Option<(Node, usize)> {
+        Self::check(state)?;
+
+        let kind = Self::marker(state.get_line(state.line))?;
+        let start_line = state.line;
+        let mut next_line = start_line + 1;
+        let mut old_line_offsets = Vec::new();
+
+        // Unlike blockquotes, every line of an alert needs the `>` marker
+        while next_line < state.line_max
+            && state.line_indent(next_line) >= 0
+            && state.line_indent(next_line) < state.md.max_indent
+            && state.get_line(next_line).starts_with('>')
+        {
+            let offsets = &state.line_offsets[next_line];
+            let line = &state.src[offsets.line_start..offsets.line_end];
+            let pos_after_marker = offsets.first_nonspace + 1 - offsets.line_start;
+            let (mut indent_after_marker, first_nonspace) = find_indent_of(line, pos_after_marker);
+
+            // Skip one optional space after `>`
+            if matches!(line[pos_after_marker..].chars().next(), Some(' ' | '\t')) {
+                indent_after_marker -= 1;
+            }
+
+            old_line_offsets.push(offsets.clone());
+
+            let offsets = &mut state.line_offsets[next_line];
+            offsets.indent_nonspace = indent_after_marker as i32;
+            offsets.first_nonspace = offsets.line_start + first_nonspace;
+            next_line += 1;
+        }
+
+        let old_indent = state.blk_indent;
+        state.blk_indent = 0;
+
+        let node = tokenize_inside(
+            state,
+            Admonition::new(kind, None),
+            start_line + 1,
+            next_line,
+        );
+
+        for (idx, offsets) in old_line_offsets.into_iter().enumerate() {
+            state.line_offsets[start_line + 1 + idx] = offsets;
+        }
+        state.blk_indent = old_indent;
+
+        Some((node, next_line - start_line))
+    }
+}
+
+// Fenced admonitions: `:::warning Optional title` up to a closing `:::`
+struct FencedAdmonitionScanner;
+
+impl FencedAdmonitionScanner {
+    fn opening(line: &str) -> Option<Admonition> {
+        let rest = line.strip_prefix(":::")?.trim();
+        let (kind, title) = match rest.split_once(char::is_whitespace) {
+            Some((kind, title)) => (kind, Some(title.trim())),
+            None => (rest, None),
+        };
+
+        // `:::details Title` is collapsible and closed by default
+        match AdmonitionKind::parse_collapsible(kind)? {
+            AdmonitionKind::Details => Some(Admonition::collapsible(
+                AdmonitionKind::Details,
+                title,
+                false,
+            )),
+            kind => Some(Admonition::new(kind, title)),
+        }
+    }
+}
+
+impl BlockRule for FencedAdmonitionScanner {
+    fn check(state: &mut BlockState) -> Option<()> {
+        if state.line_indent(state.line) >= state.md.max_indent {
+            return None;
+        }
+
+        Self::opening(state.get_line(state.line)).map(|_| ())
+    }
+
+    fn run(state: &mut BlockState) -> Option<(Node, usize)> {
+        Self::check(state)?;
+
+        let admonition = Self::opening(state.get_line(state.line))?;
+        let start_line = state.line;
+        let end_line = find_closing_fence(state, start_line + 1);
+
+        let node = tokenize_inside(state, admonition, start_line + 1, end_line);
+
+        // An admonition that's never closed runs to the end of the document, like a code fence
+        let len = if end_line < state.line_max {
+            end_line + 1 - start_line
+        } else {
+            end_line - start_line
+        };
+
+        Some((node, len))
+    }
+}
+
+// MkDocs-style collapsible callouts: `??? note "Optional title"` followed by content indented by
+// four spaces. `???+` makes the callout open by default.
+struct CollapsibleScanner;
+
+impl CollapsibleScanner {
+    const CONTENT_INDENT: i32 = 4;
+
+    fn opening(line: &str) -> Option<Admonition> {
+        let rest = line.strip_prefix("???")?;
+        let (open, rest) = match rest.strip_prefix('+') {
+            Some(rest) => (true, rest),
+            None => (false, rest),
+        };
+
+        // The type needs to be separated from the marker
+        if !rest.starts_with([' ', '\t']) {
+            return None;
+        }
+
+        let rest = rest.trim();
+        let (kind, title) = match rest.split_once(char::is_whitespace) {
+            Some((kind, title)) => (kind, Some(title.trim().trim_matches('"'))),
+            None => (rest, None),
+        };
+
+        Some(Admonition::collapsible(
+            AdmonitionKind::parse_collapsible(kind)?,
+            title.filter(|title| !title.is_empty()),
+            open,
+        ))
+    }
+}
+
+impl BlockRule for CollapsibleScanner {
+    fn check(state: &mut BlockState) -> Option<()> {
+        if state.line_indent(state.line) >= state.md.max_indent {
+            return None;
+        }
+
+        Self::opening(state.get_line(state.line)).map(|_| ())
+    }
+
+    fn run(state: &mut BlockState) -> Option<(Node, usize)> {
+        Self::check(state)?;
+
+        let admonition = Self::opening(state.get_line(state.line))?;
+        let start_line = state.line;
+        let content_indent = state.line_offsets[start_line].indent_nonspace + Self::CONTENT_INDENT;
+
+        let end_line = indented_block_end(state, start_line, content_indent);
+
+        let old_indent = state.blk_indent;
+        state.blk_indent = content_indent as usize;
+        let node = tokenize_inside(state, admonition, start_line + 1, end_line);
+        state.blk_indent = old_indent;
+
+        Some((node, end_line - start_line))
+    }
+}
+
+// Finds the end of content indented by at least `content_indent` below `start_line`: the first
+// non-empty line that isn't indented enough (trailing empty lines aren't part of the content)
+pub(super) fn indented_block_end(
+    state: &BlockState,
+    start_line: usize,
+    content_indent: i32,
+) -> usize {
+    let mut end_line = start_line + 1;
+    let mut next_line = start_line + 1;
+
+    while next_line < state.line_max {
+        if !state.is_empty(next_line) {
+            if state.line_offsets[next_line].indent_nonspace < content_indent {
+                break;
+            }
+            end_line = next_line + 1;
+        }
+        next_line += 1;
+    }
+
+    end_line
+}
+
+// The character and length of a ``` or ~~~ code fence at the start of a line
+fn code_fence(text: &str) -> Option<(char, usize)> {
+    let marker = text.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = text.chars().take_while(|c| *c == marker).count();
+
+    (len >= 3).then_some((marker, len))
+}
+
+// Finds the `:::` line that closes the block opened just before `from`. Other `:::name` lines
+// open nested blocks, so their closing lines are skipped, as are lines in code blocks.
+pub(super) fn find_closing_fence(state: &BlockState, from: usize) -> usize {
+    let mut depth = 1;
+    let mut line = from;
+    let mut code: Option<(char, usize)> = None;
+
+    while line < state.line_max {
+        // Outdented lines end the enclosing list item and with it the block
+        if state.line_indent(line) < 0 {
+            break;
+        }
+
+        let text = state.get_line(line).trim_end();
+        let indented = state.line_indent(line) >= state.md.max_indent;
+
+        if let Some((marker, len)) = code_fence(text).filter(|_| !indented) {
+            match code {
+                // Info strings of backtick fences can't contain backticks
+                None if marker == '~' || !text[len..].contains('`') => code = Some((marker, len)),
+                Some((open, open_len))
+                    if marker == open && len >= open_len && text[len..].trim().is_empty() =>
+                {
+                    code = None
+                }
+                _ => {}
+            }
+        } else if code.is_none() && !indented && text.starts_with(":::") {
+            if text[3..].trim().is_empty() {
+                depth -= 1;
+
+                if depth == 0 {
+                    break;
+                }
+            } else {
+                depth += 1;
+            }
+        }
+
+        line += 1;
+    }
+
+    line
+}
+
+// Parses the lines in `start..end` as the children of a new container node
+pub(super) fn tokenize_inside<T: NodeValue>(
+    state: &mut BlockState,
+    value: T,
+    start: usize,
+    end: usize,
+) -> Node {
+    let start_line = state.line;
+    let old_node = std::mem::replace(&mut state.node, Node::new(value));
+    let old_line_max = state.line_max;
+
+    state.line = start;
+    state.line_max = end;
+    state.md.block.tokenize(state);
+    state.line = start_line;
+    state.line_max = old_line_max;
+
+    std::mem::replace(&mut state.node, old_node)
+}
+
+// Like tokenize_inside, but the content starts partway into `line`, after a marker like `[^1]:`,
+// and goes on in the lines below it that are indented by `content_indent`. A paragraph can also
+// go on in lazy continuation lines that aren't indented, as in list items, but not past `limit`.
+// Returns the node and the line after the content.
+pub(super) fn tokenize_hanging<T: NodeValue>(
+    state: &mut BlockState,
+    value: T,
+    line: usize,
+    content_start: usize,
+    content_indent: i32,
+    limit: usize,
+) -> (Node, usize) {
+    let old_offsets = state.line_offsets[line].clone();
+    let old_indent = state.blk_indent;
+    let old_line = state.line;
+    let old_line_max = state.line_max;
+    let old_node = std::mem::replace(&mut state.node, Node::new(value));
+
+    state.line_offsets[line].first_nonspace = content_start;
+    state.line_offsets[line].indent_nonspace = content_indent;
+    state.blk_indent = content_indent as usize;
+    state.line = line;
+    state.line_max = limit;
+
+    // Tokenizing stops at the first line that's neither indented enough nor a lazy continuation
+    state.md.block.tokenize(state);
+
+    // Trailing empty lines aren't part of the content
+    let mut end = state.line.max(line + 1);
+    while end > line + 1 && state.is_empty(end - 1) {
+        end -= 1;
+    }
+
+    state.line = old_line;
+    state.line_max = old_line_max;
+    state.blk_indent = old_indent;
+    state.line_offsets[line] = old_offsets;
+
+    (std::mem::replace(&mut state.node, old_node), end)
+}
+
+// Where the content after a marker at the start of `line` begins, skipping the whitespace between
+// them, and how far it's indented
+pub(super) fn content_after_marker(
+    state: &BlockState,
+    line: usize,
+    marker_len: usize,
+) -> (usize, i32) {
+    let offsets = &state.line_offsets[line];
+    let marker_end = offsets.first_nonspace + marker_len;
+    let spaces = state.src[marker_end..offsets.line_end]
+        .chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .count();
+
+    (
+        marker_end + spaces,
+        offsets.indent_nonspace + (marker_len + spaces) as i32,
+    )
+}
+
+pub(super) fn add_admonition_rules(md: &mut MarkdownIt) {
+    md.block
+        .add_rule::<AlertScanner>()
+        .before::<BlockquoteScanner>();
+    md.block
+        .add_rule::<FencedAdmonitionScanner>()
+        .before::<BlockquoteScanner>();
+    md.block
+        .add_rule::<CollapsibleScanner>()
+        .before::<BlockquoteScanner>();
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::assign_callout_ids;
+    use crate::{
+        md::{ast, build_search_index_for_page, render, SearchDocument, TableOfContents, TocEntry},
+        tests::test_markdown_produces_expected_html,
+    };
+
+    #[test]
+    fn admonitions() {
+        let cases: Vec<(&str, &str)> = vec![
+            (
+                indoc! {"
+                    > [!NOTE]
+                    > Some **useful** info.
+                    >
+                    > Another paragraph.
+                "},
+                indoc! {r#"
+                    <div class="admonition admonition-note" role="note">
+                    <p class="admonition-title">Note</p>
+                    <p>Some <strong>useful</strong> info.</p>
+                    <p>Another paragraph.</p>
+                    </div>
+                "#},
+            ),
+            (
+                indoc! {"
+                    > [!warning]
+                    > Careful.
+                    Not part of the alert.
+                "},
+                indoc! {r#"
+                    <div class="admonition admonition-warning" role="note">
+                    <p class="admonition-title">Warning</p>
+                    <p>Careful.</p>
+                    </div>
+                    <p>Not part of the alert.</p>
+                "#},
+            ),
+            (
+                indoc! {"
+                    > [!UNKNOWN]
+                    > Just a quote.
+                "},
+                indoc! {"
+                    <blockquote>
+                    <p>[!UNKNOWN]
+                    Just a quote.</p>
+                    </blockquote>
+                "},
+            ),
+            (
+                indoc! {"
+                    :::warning Breaking <changes>
+                    Read this first.
+
+                    :::tip
+                    Nested.
+                    :::
+
+                    - And a list
+                    :::
+
+                    After.
+                "},
+                indoc! {r#"
+                    <div class="admonition admonition-warning" role="note">
+                    <p class="admonition-title">Breaking &lt;changes&gt;</p>
+                    <p>Read this first.</p>
+                    <div class="admonition admonition-tip" role="note">
+                    <p class="admonition-title">Tip</p>
+                    <p>Nested.</p>
+                    </div>
+                    <ul>
+                    <li>And a list</li>
+                    </ul>
+                    </div>
+                    <p>After.</p>
+                "#},
+            ),
+            (
+                indoc! {"
+                    :::note
+                    ````
+                    :::
+                    ```
+                    ````
+                    :::
+                "},
+                indoc! {r#"
+                    <div class="admonition admonition-note" role="note">
+                    <p class="admonition-title">Note</p>
+                    <pre class="language-text"><code><span class="line">:::</span>
+                    <span class="line">```</span>
+                    </code></pre>
+                    </div>
+                "#},
+            ),
+            (
+                indoc! {"
+                    :::danger
+                    Never closed.
+                "},
+                indoc! {r#"
+                    <div class="admonition admonition-danger" role="note">
+                    <p class="admonition-title">Danger</p>
+                    <p>Never closed.</p>
+                    </div>
+                "#},
+            ),
+            (":::unknown\n", "<p>:::unknown</p>\n"),
+        ];
+
+        test_markdown_produces_expected_html(cases);
+    }
+
+    #[test]
+    fn admonitions_in_search_and_toc() {
+        let tree = ast(indoc! {"
+            ## Setup
+
+            > [!TIP]
+            > Use the installer.
+
+            :::warning Careful
+            First.
+
+            ## Not in the TOC
+
+            Second.
+            :::
+        "});
+
+        assert_eq!(
+            TableOfContents::parse(&tree),
+            TableOfContents::new(vec![TocEntry::new(2, "Setup", TableOfContents::empty())])
+        );
+
+        let index = build_search_index_for_page("Guide", "/guide", &[], &tree);
+
+        assert_eq!(
+            index.0[1],
+            SearchDocument::new(
+                2,
+                "/guide",
+                Some("setup"),
+                &[],
+                "Guide",
+                "Setup",
+                "Tip Use the installer. Careful First. Not in the TOC Second.",
+            )
+        );
+    }
+
+    #[test]
+    fn collapsible_callouts() {
+        let cases: Vec<(&str, &str)> = vec![
+            (
+                indoc! {"
+                    :::details Advanced options
+                    Hidden by default.
+                    :::
+                "},
+                indoc! {r#"
+                    <details class="admonition admonition-details" id="callout-guide-advanced-options">
+                    <summary class="admonition-title">Advanced options</summary>
+                    <p>Hidden by default.</p>
+                    </details>
+                "#},
+            ),
+            (
+                indoc! {r#"
+                    ??? note "Why?"
+                        Because.
+
+                        - Really
+
+                    Not in the callout.
+                "#},
+                indoc! {r#"
+                    <details class="admonition admonition-note" id="callout-guide-why">
+                    <summary class="admonition-title">Why?</summary>
+                    <p>Because.</p>
+                    <ul>
+                    <li>Really</li>
+                    </ul>
+                    </details>
+                    <p>Not in the callout.</p>
+                "#},
+            ),
+            (
+                indoc! {"
+                    ???+ tip
+                        Open by default.
+
+                    ??? tip
+                        Same title.
+                "},
+                indoc! {r#"
+                    <details class="admonition admonition-tip" id="callout-guide-tip" open="">
+                    <summary class="admonition-title">Tip</summary>
+                    <p>Open by default.</p>
+                    </details>
+                    <details class="admonition admonition-tip" id="callout-guide-tip-2">
+                    <summary class="admonition-title">Tip</summary>
+                    <p>Same title.</p>
+                    </details>
+                "#},
+            ),
+            ("???nope\n", "<p>???nope</p>\n"),
+        ];
+
+        for (md, expected) in cases {
+            let mut tree = ast(md);
+            assign_callout_ids(&mut tree, "/guide");
+            assert_eq!(render(&tree), expected);
+        }
+    }
+
+    #[test]
+    fn collapsed_content_is_searchable() {
+        let tree = ast(indoc! {"
+            ## Options
+
+            ??? info \"More\"
+                Hidden text.
+        "});
+
+        let index = build_search_index_for_page("Guide", "/guide", &[], &tree);
+
+        assert_eq!(
+            index.0[1],
+            SearchDocument::new(
+                2,
+                "/guide",
+                Some("options"),
+                &[],
+                "Guide",
+                "Options",
+                "More Hidden text.",
+            )
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use markdown_it::{parser::extset::RenderExtSet, Node, Renderer};
+
+// A renderer that only keeps the `id` attributes, so that links can be checked against the
+// elements that end up on the page (headings nested in callouts or tabs, footnotes, and so on)
+#[derive(Default)]
+struct AnchorCollector {
+    ids: Vec<String>,
+    ext: RenderExtSet,
+}
+
+impl AnchorCollector {
+    fn collect(&mut self, attrs: &[(&str, String)]) {
+        self.ids.extend(
+            attrs
+                .iter()
+                .filter(|(name, _)| *name == "id")
+                .map(|(_, value)| value.clone()),
+        );
+    }
+}
+
+impl Renderer for AnchorCollector {
+    fn open(&mut self, _: &str, attrs: &[(&str, String)]) {
+        self.collect(attrs);
+    }
+
+    fn close(&mut self, _: &str) {}
+
+    fn self_close(&mut self, _: &str, attrs: &[(&str, String)]) {
+        self.collect(attrs);
+    }
+
+    fn contents(&mut self, nodes: &[Node]) {
+        for node in nodes {
+            node.node_value.render(node, self);
+        }
+    }
+
+    fn cr(&mut self) {}
+
+    fn text(&mut self, _: &str) {}
+
+    fn text_raw(&mut self, _: &str) {}
+
+    fn ext(&mut self) -> &mut RenderExtSet {
+        &mut self.ext
+    }
+}
+
+// Every id that rendering the document produces
+pub(crate) fn anchor_ids(root: &Node) -> Vec<String> {
+    let mut collector = AnchorCollector::default();
+    root.node_value.render(root, &mut collector);
+    collector.ids
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::md::ast;
+
+    use super::anchor_ids;
+
+    #[test]
+    fn ids_on_the_page() {
+        let tree = ast(indoc! {r#"
+            # Not rendered
+
+            ## Top
+
+            :::note
+            ## Inside note
+            :::
+
+            === "Rust"
+                ## In tab
+
+            Text[^1].
+
+            [^1]: A footnote.
+        "#});
+
+        assert_eq!(
+            anchor_ids(&tree),
+            vec![
+                "top",
+                "inside-note",
+                "tabs-1-tab-1",
+                "tabs-1-panel-1",
+                "in-tab",
+                "fnref-1",
+                "fn-1",
+            ]
+        );
+    }
+}
@@ -0,0 +1,225 @@
+use markdown_it::{
+    parser::core::CoreRule, plugins::cmark::block::list::OrderedList, MarkdownIt, Node, Renderer,
+};
+
+use super::code::FancyCodeBlock;
+
+// Comment openers that can precede an annotation marker, longest first so that `<!--` isn't
+// mistaken for `--`
+const COMMENT_OPENERS: [&str; 7] = ["<!--", "//", "/*", "#", "--", ";", "%"];
+const COMMENT_CLOSERS: [&str; 2] = ["*/", "-->"];
+
+// Numbered markers in a code block, linked to the items of the ordered list that follows it
+#[derive(Debug)]
+pub(super) struct Annotations {
+    id_prefix: String,
+    // Line index and annotation number
+    markers: Vec<(usize, usize)>,
+}
+
+impl Annotations {
+    fn id(&self, number: usize) -> String {
+        format!("{}-{number}", self.id_prefix)
+    }
+
+    pub(super) fn render_markers(&self, line: usize, fmt: &mut dyn Renderer) {
+        for (_, number) in self.markers.iter().filter(|(l, _)| *l == line) {
+            let attrs = vec![
+                ("class", String::from("code-annotation")),
+                ("href", format!("#{}", self.id(*number))),
+                ("aria-label", format!("Annotation {number}")),
+            ];
+
+            fmt.open("a", &attrs);
+            fmt.text(&number.to_string());
+            fmt.close("a");
+        }
+    }
+}
+
+// Finds a marker like `// (1)`, `# (2)` or `/* (3) */` at the end of a line and returns where the
+// comment starts along with the annotation number
+fn find_marker(line: &str) -> Option<(usize, usize)> {
+    let mut rest = line.trim_end();
+
+    if let Some(closer) = COMMENT_CLOSERS.iter().find(|c| rest.ends_with(*c)) {
+        rest = rest[..rest.len() - closer.len()].trim_end();
+    }
+
+    let inner = rest.strip_suffix(')')?;
+    let open = inner.rfind('(')?;
+    let digits = &inner[open + 1..];
+
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let before = inner[..open].trim_end();
+    let opener = COMMENT_OPENERS.iter().find(|o| before.ends_with(*o))?;
+    let start = before[..before.len() - opener.len()].trim_end().len();
+
+    Some((start, digits.parse().ok()?))
+}
+
+fn has_markers(code: &FancyCodeBlock) -> bool {
+    code.content.lines().any(|line| find_marker(line).is_some())
+}
+
+// Removes the markers from the code so that they don't get highlighted as comments
+fn strip_markers(code: &mut FancyCodeBlock, id_prefix: String) {
+    let mut content = String::with_capacity(code.content.len());
+    let mut markers: Vec<(usize, usize)> = Vec::new();
+
+    for (idx, line) in code.content.lines().enumerate() {
+        match find_marker(line) {
+            Some((start, number)) => {
+                content.push_str(&line[..start]);
+                markers.push((idx, number));
+            }
+            None => content.push_str(line),
+        }
+        content.push('\n');
+    }
+
+    code.content = content;
+    code.annotations = Some(Annotations { id_prefix, markers });
+}
+
+// Moves an ordered list that directly follows a code block with markers into the code block,
+// which renders it as the list of annotations
+fn attach_annotations(node: &mut Node, count: &mut usize) {
+    let mut idx = 0;
+
+    while idx + 1 < node.children.len() {
+        let is_annotated = node.children[idx]
+            .cast::<FancyCodeBlock>()
+            .is_some_and(|code| code.annotations.is_none() && has_markers(code))
+            && node.children[idx + 1].is::<OrderedList>();
+
+        if is_annotated {
+            *count += 1;
+            let id_prefix = format!("annotation-{count}");
+
+            let mut list = node.children.remove(idx + 1);
+            let start = list.cast::<OrderedList>().map_or(1, |l| l.start as usize);
+
+            list.attrs.push(("class", String::from("code-annotations")));
+            for (n, item) in list.children.iter_mut().enumerate() {
+                item.attrs
+                    .push(("id", format!("{id_prefix}-{}", start + n)));
+            }
+
+            let code_node = &mut node.children[idx];
+            if let Some(code) = code_node.cast_mut::<FancyCodeBlock>() {
+                strip_markers(code, id_prefix);
+            }
+            code_node.children.push(list);
+        }
+
+        idx += 1;
+    }
+}
+
+// Runs when the page is parsed and again once included code is filled in, so blocks that are
+// already annotated are counted to keep the ids unique
+pub(super) fn annotate_code_blocks(root: &mut Node) {
+    let mut count = 0;
+    root.walk(|node, _| {
+        if node
+            .cast::<FancyCodeBlock>()
+            .is_some_and(|code| code.annotations.is_some())
+        {
+            count += 1;
+        }
+    });
+
+    root.walk_mut(|node, _| attach_annotations(node, &mut count));
+}
+
+struct CodeAnnotationsRule;
+
+impl CoreRule for CodeAnnotationsRule {
+    fn run(root: &mut Node, _: &MarkdownIt) {
+        annotate_code_blocks(root);
+    }
+}
+
+pub(super) fn add_code_annotations_rule(md: &mut MarkdownIt) {
+    md.add_rule::<CodeAnnotationsRule>();
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::md::{ast, build_search_index_for_page, render};
+
+    use super::find_marker;
+
+    #[test]
+    fn markers() {
+        let cases: Vec<(&str, Option<(usize, usize)>)> = vec![
+            ("let x = 1; // (1)", Some((10, 1))),
+            ("x = 1  # (12)  ", Some((5, 12))),
+            ("int x; /* (3) */", Some((6, 3))),
+            ("<p></p> <!-- (2) -->", Some((7, 2))),
+            ("// (4)", Some((0, 4))),
+            ("x = f(1)", None),
+            ("// ()", None),
+            ("// (a)", None),
+            ("# (+1)", None),
+            ("let x = 1; // see (1) below", None),
+        ];
+
+        for (line, expected) in cases {
+            assert_eq!(find_marker(line), expected, "{line}");
+        }
+    }
+
+    #[test]
+    fn annotated_code_block() {
+        let md = indoc! {"
+            ```python
+            x = 1  # (1)
+            y = 2  # (2)
+            ```
+
+            1. The first value
+            2. The second value
+
+            ```python
+            z = 3  # (1)
+            ```
+
+            Not an annotation list.
+
+            1. Just a list
+        "};
+
+        let tree = ast(md);
+        let html = render(&tree);
+        let (annotated, plain) = html.split_once("</ol>").unwrap();
+
+        assert!(!annotated.contains("(1)"));
+        assert!(html.contains(
+            "<a class=\"code-annotation\" href=\"#annotation-1-1\" aria-label=\"Annotation 1\">1</a></span>\n"
+        ));
+        assert!(html.contains(
+            "<a class=\"code-annotation\" href=\"#annotation-1-2\" aria-label=\"Annotation 2\">2</a></span>\n"
+        ));
+        assert!(annotated.contains(indoc! {r#"
+            </code></pre>
+            <ol class="code-annotations">
+            <li id="annotation-1-1">The first value</li>
+            <li id="annotation-1-2">The second value</li>
+        "#}));
+
+        // Without a list right after it, the second block is left alone
+        assert!(plain.contains("(1)"));
+        assert!(html.contains("<ol>\n<li>Just a list</li>\n</ol>"));
+
+        // The explanations are still searchable
+        let index = build_search_index_for_page("Code", "/code", &[], &tree);
+        assert!(format!("{index:?}").contains("The first value The second value"));
+    }
+}
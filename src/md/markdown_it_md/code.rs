@@ -1,87 +1,330 @@
+use std::{ops::RangeInclusive, path::Path};
+
 use markdown_it::{
+    common::utils::escape_html,
     parser::core::CoreRule,
     plugins::cmark::block::{code::CodeBlock, fence::CodeFence},
     MarkdownIt, Node, NodeValue, Renderer,
 };
 
-use super::highlight::Highlighter;
+use super::{
+    annotation::{add_code_annotations_rule, Annotations},
+    highlight::Highlighter,
+    include::Include,
+    links::line_at,
+};
+
+// Diagrams are drawn in the browser rather than highlighted
+const MERMAID: &str = "mermaid";
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Eq, PartialEq)]
 struct Metadata {
     language: Option<String>,
     show_line_numbers: bool,
     file: Option<String>,
+    highlighted_lines: LineRanges,
+    include: Option<Include>,
 }
 
 impl Metadata {
+    // The language comes first and is followed by the other attributes in any order, e.g.
+    // `rust file="src/main.rs" showLineNumbers {3,5-7}`. Unknown attributes are ignored.
     fn parse(s: &str) -> Self {
-        let mut metadata = Metadata {
-            language: None,
-            show_line_numbers: false,
-            file: None,
-        };
-        let mut parts = s.split_whitespace();
+        let mut metadata = Metadata::default();
+        let mut include = Include::default();
+        let mut attributes = parse_attributes(s).into_iter().peekable();
 
-        if let Some(part) = parts.next() {
-            metadata.language = Some(String::from(part));
+        if let Some((language, None)) =
+            attributes.next_if(|(key, value)| value.is_none() && !key.starts_with('{'))
+        {
+            metadata.language = Some(language);
         }
 
-        for part in parts {
-            if part == "showLineNumbers" {
-                metadata.show_line_numbers = true;
+        for (key, value) in attributes {
+            match (key.as_str(), value) {
+                ("showLineNumbers", None) => metadata.show_line_numbers = true,
+                ("showLineNumbers", Some(value)) => metadata.show_line_numbers = value == "true",
+                ("file", Some(file)) => metadata.file = Some(file),
+                ("highlight", Some(ranges)) => metadata
+                    .highlighted_lines
+                    .extend(parse_line_ranges(&ranges)),
+                (group, None) if group.starts_with('{') => metadata
+                    .highlighted_lines
+                    .extend(parse_line_ranges(group.trim_matches(['{', '}']))),
+                ("include", Some(path)) => include.path = path,
+                ("lines", Some(ranges)) => include.lines = parse_line_ranges(&ranges),
+                ("region", Some(region)) => include.region = Some(region),
+                _ => {}
             }
+        }
+
+        if !include.path.is_empty() {
+            metadata.include = Some(include);
+        }
+
+        metadata
+    }
+
+    fn is_highlighted(&self, line: usize) -> bool {
+        self.highlighted_lines
+            .iter()
+            .any(|range| range.contains(&line))
+    }
+}
+
+pub(super) type LineRanges = Vec<RangeInclusive<usize>>;
+
+// Parses 1-based line numbers and ranges like `3,5-7`, skipping anything that isn't one. Ranges
+// written backwards, like `7-5`, are turned around.
+pub(super) fn parse_line_ranges(s: &str) -> LineRanges {
+    s.split(',')
+        .filter_map(|part| {
+            let part = part.trim();
 
-            if let Some(file) = {
-                let parts: Vec<&str> = s.split('=').collect();
-                if parts.len() == 2 && parts[0].trim() == "file" {
-                    let filename = parts[1].trim().trim_matches('"');
-                    Some(filename.to_string())
-                } else {
-                    None
+            match part.split_once('-') {
+                Some((start, end)) => {
+                    let start: usize = start.trim().parse().ok()?;
+                    let end: usize = end.trim().parse().ok()?;
+                    Some(start.min(end)..=start.max(end))
+                }
+                None => {
+                    let line = part.parse().ok()?;
+                    Some(line..=line)
+                }
+            }
+        })
+        .collect()
+}
+
+type Attributes = Vec<(String, Option<String>)>;
+
+// Splits an info string into whitespace-separated flags (`showLineNumbers`) and `key=value`
+// pairs, where values can be wrapped in single or double quotes to include whitespace. Braced
+// groups like `{1, 3}` are kept together as a single flag.
+fn parse_attributes(s: &str) -> Attributes {
+    let mut attributes: Attributes = Vec::new();
+    let mut chars = s.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+        if chars.peek().is_none() {
+            break;
+        }
+
+        if chars.next_if_eq(&'{').is_some() {
+            let mut group = String::from("{");
+            for c in chars.by_ref() {
+                group.push(c);
+                if c == '}' {
+                    break;
                 }
-            } {
-                metadata.file = Some(file);
             }
+            attributes.push((group, None));
+            continue;
         }
-        metadata
+
+        let mut key = String::new();
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != '=' && *c != '{') {
+            key.push(c);
+        }
+
+        if chars.next_if_eq(&'=').is_none() {
+            attributes.push((key, None));
+            continue;
+        }
+
+        let mut value = String::new();
+        match chars.next_if(|c| *c == '"' || *c == '\'') {
+            Some(quote) => {
+                for c in chars.by_ref() {
+                    if c == quote {
+                        break;
+                    }
+                    value.push(c);
+                }
+            }
+            None => {
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                    value.push(c);
+                }
+            }
+        }
+
+        attributes.push((key, Some(value)));
     }
+
+    attributes
 }
 
 #[derive(Debug)]
-struct FancyCodeBlock {
+pub(super) struct FancyCodeBlock {
     meta: Metadata,
-    content: String,
+    pub(super) content: String,
+    pub(super) annotations: Option<Annotations>,
+    highlighter: Highlighter,
+}
+
+impl FancyCodeBlock {
+    pub(super) fn include(&self) -> Option<&Include> {
+        self.meta.include.as_ref()
+    }
+
+    // The language of included code defaults to the file's extension
+    pub(super) fn set_included_content(&mut self, content: String, path: &Path) {
+        if self.meta.language.is_none() {
+            self.meta.language = path
+                .extension()
+                .map(|ext| ext.to_string_lossy().into_owned());
+        }
+
+        self.content = content;
+    }
+
+    fn is_diagram(&self) -> bool {
+        self.meta.language.as_deref() == Some(MERMAID)
+    }
+
+    // The diagram source is left as text for the renderer that the page template loads
+    fn render_diagram(&self, fmt: &mut dyn Renderer) {
+        fmt.cr();
+        fmt.open("pre", &[("class", String::from(MERMAID))]);
+        fmt.text(&self.content);
+        fmt.close("pre");
+        fmt.cr();
+    }
+
+    fn render_lines(&self, lines: &[String], fmt: &mut dyn Renderer) {
+        for (idx, line) in lines.iter().enumerate() {
+            let class = if self.meta.is_highlighted(idx + 1) {
+                "line highlighted"
+            } else {
+                "line"
+            };
+
+            fmt.open("span", &[("class", String::from(class))]);
+            if self.meta.show_line_numbers {
+                fmt.open(
+                    "span",
+                    &[
+                        ("class", String::from("line-number")),
+                        ("aria-hidden", String::from("true")),
+                    ],
+                );
+                fmt.text(&(idx + 1).to_string());
+                fmt.close("span");
+            }
+            fmt.text_raw(line);
+            if let Some(annotations) = &self.annotations {
+                annotations.render_markers(idx, fmt);
+            }
+            fmt.close("span");
+            fmt.text_raw("\n");
+        }
+    }
 }
 
 impl NodeValue for FancyCodeBlock {
-    fn render(&self, _: &Node, fmt: &mut dyn Renderer) {
+    fn render(&self, node: &Node, fmt: &mut dyn Renderer) {
+        if self.is_diagram() {
+            return self.render_diagram(fmt);
+        }
+
         // TODO: make default language configurable
         let default_lang = String::from("text");
         let lang = self.meta.language.as_ref().unwrap_or(&default_lang);
 
-        let pre_attrs = vec![("class", format!("language-{}", lang))];
-
-        let higlighter = Highlighter::default();
-
-        let code = match higlighter.highlight(lang, &self.content) {
-            Ok(html) => html,
-            Err(e) => e.to_string(),
-        };
+        let mut classes = vec![format!("language-{}", lang)];
+        if self.meta.show_line_numbers {
+            classes.push(String::from("line-numbers"));
+        }
+        let mut pre_attrs = vec![("class", classes.join(" "))];
+        if let Some(style) = self.highlighter.pre_style() {
+            pre_attrs.push(("style", style));
+        }
 
         fmt.cr();
+        if let Some(file) = &self.meta.file {
+            fmt.open("figure", &[("class", String::from("code-block"))]);
+            fmt.open("figcaption", &[("class", String::from("code-file"))]);
+            fmt.text(file);
+            fmt.close("figcaption");
+        }
         fmt.open("pre", &pre_attrs);
         fmt.open("code", &[]);
-        fmt.text_raw(&code);
+        // Code in languages without a syntax is shown as is (and reported when the page is built)
+        let lines = self
+            .highlighter
+            .highlight_lines(lang, &self.content)
+            .unwrap_or_else(|_| {
+                self.content
+                    .lines()
+                    .map(|line| escape_html(line).into_owned())
+                    .collect()
+            });
+        self.render_lines(&lines, fmt);
         fmt.close("code");
         fmt.close("pre");
+        if self.meta.file.is_some() {
+            fmt.close("figure");
+        }
+        // The list of annotations, if any
+        fmt.contents(&node.children);
         fmt.cr();
     }
 }
 
+// Pages with diagrams need the diagram renderer
+pub(crate) fn has_diagrams(root: &Node) -> bool {
+    let mut found = false;
+
+    root.walk(|node, _| {
+        found |= node
+            .cast::<FancyCodeBlock>()
+            .is_some_and(FancyCodeBlock::is_diagram);
+    });
+
+    found
+}
+
+// A code block in a language that can't be highlighted, with its line in the Markdown source
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) struct UnknownLanguage {
+    pub(crate) language: String,
+    pub(crate) line: Option<usize>,
+}
+
+// Code blocks without a language aren't meant to be highlighted so they aren't included
+pub(crate) fn unknown_languages(root: &Node, source: &str) -> Vec<UnknownLanguage> {
+    let mut unknown: Vec<UnknownLanguage> = Vec::new();
+
+    root.walk(|node, _| {
+        let Some(code) = node.cast::<FancyCodeBlock>() else {
+            return;
+        };
+
+        match &code.meta.language {
+            Some(language) if !code.is_diagram() && !code.highlighter.has_language(language) => {
+                unknown.push(UnknownLanguage {
+                    language: language.clone(),
+                    line: node
+                        .srcmap
+                        .map(|pos| line_at(source, pos.get_byte_offsets().0)),
+                })
+            }
+            _ => {}
+        }
+    });
+
+    unknown
+}
+
 struct FancyCodeBlockRule;
 
 impl CoreRule for FancyCodeBlockRule {
-    fn run(root: &mut Node, _: &MarkdownIt) {
+    fn run(root: &mut Node, md: &MarkdownIt) {
+        let highlighter = md.ext.get::<Highlighter>().cloned().unwrap_or_default();
+
         root.walk_post_mut(|node, _| {
             let mut meta: Option<Metadata> = None;
             let mut content: Option<&String> = None;
@@ -98,12 +341,222 @@ impl CoreRule for FancyCodeBlockRule {
                 node.replace(FancyCodeBlock {
                     meta: meta.unwrap_or_default(),
                     content: String::from(content),
+                    annotations: None,
+                    highlighter: highlighter.clone(),
                 })
             }
         })
     }
 }
 
-pub(super) fn add_code_block_rule(md: &mut MarkdownIt) {
+pub(super) fn add_code_block_rule(md: &mut MarkdownIt, highlighter: &Highlighter) {
+    md.ext.insert(highlighter.clone());
     md.add_rule::<FancyCodeBlockRule>();
+    add_code_annotations_rule(md);
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::{md::ast, tests::test_markdown_produces_expected_html};
+
+    use super::{
+        has_diagrams, parse_attributes, unknown_languages, Attributes, LineRanges, Metadata,
+        UnknownLanguage,
+    };
+
+    #[test]
+    fn attributes() {
+        let attr = |key: &str, value: Option<&str>| (String::from(key), value.map(String::from));
+
+        let cases: Vec<(&str, Attributes)> = vec![
+            ("", vec![]),
+            ("rust", vec![attr("rust", None)]),
+            (
+                "rust  showLineNumbers\tfile=main.rs",
+                vec![
+                    attr("rust", None),
+                    attr("showLineNumbers", None),
+                    attr("file", Some("main.rs")),
+                ],
+            ),
+            (
+                "file=\"my file.rs\" title='It is' x=",
+                vec![
+                    attr("file", Some("my file.rs")),
+                    attr("title", Some("It is")),
+                    attr("x", Some("")),
+                ],
+            ),
+        ];
+
+        for (info, expected) in cases {
+            assert_eq!(parse_attributes(info), expected, "{info}");
+        }
+    }
+
+    #[test]
+    fn metadata() {
+        let meta = |language: Option<&str>, show_line_numbers: bool, file: Option<&str>| Metadata {
+            language: language.map(String::from),
+            show_line_numbers,
+            file: file.map(String::from),
+            ..Metadata::default()
+        };
+
+        let cases: Vec<(&str, Metadata)> = vec![
+            ("", meta(None, false, None)),
+            ("rust", meta(Some("rust"), false, None)),
+            ("rust showLineNumbers", meta(Some("rust"), true, None)),
+            (
+                "rust file=src/main.rs",
+                meta(Some("rust"), false, Some("src/main.rs")),
+            ),
+            (
+                "rust file=\"src/my main.rs\" showLineNumbers",
+                meta(Some("rust"), true, Some("src/my main.rs")),
+            ),
+            (
+                "rust showLineNumbers=false file='a.rs' other=1",
+                meta(Some("rust"), false, Some("a.rs")),
+            ),
+            ("file=a.rs showLineNumbers", meta(None, true, Some("a.rs"))),
+        ];
+
+        for (info, expected) in cases {
+            assert_eq!(Metadata::parse(info), expected, "{info}");
+        }
+    }
+
+    #[test]
+    fn highlighted_lines() {
+        let cases: Vec<(&str, Option<&str>, LineRanges)> = vec![
+            ("rust", Some("rust"), vec![]),
+            ("rust {3,5-7}", Some("rust"), vec![3..=3, 5..=7]),
+            ("rust{2}", Some("rust"), vec![2..=2]),
+            (
+                "rust { 1, 4 - 5 } showLineNumbers",
+                Some("rust"),
+                vec![1..=1, 4..=5],
+            ),
+            ("rust highlight=\"2-4\"", Some("rust"), vec![2..=4]),
+            ("rust highlight=2,x,9-", Some("rust"), vec![2..=2]),
+            ("rust {5-1}", Some("rust"), vec![1..=5]),
+            ("{1}", None, vec![1..=1]),
+        ];
+
+        for (info, language, lines) in cases {
+            let meta = Metadata::parse(info);
+            assert_eq!(meta.language.as_deref(), language, "{info}");
+            assert_eq!(meta.highlighted_lines, lines, "{info}");
+        }
+    }
+
+    #[test]
+    fn code_block_render() {
+        let cases: Vec<(&str, &str)> = vec![
+            (
+                indoc! {"
+                    ```python showLineNumbers
+                    a = 1
+                    b = 2
+                    ```
+                "},
+                concat!(
+                    "<pre class=\"language-python line-numbers\"><code>",
+                    "<span class=\"line\"><span class=\"line-number\" aria-hidden=\"true\">1</span><span class=\"source python\"><span class=\"meta qualified-name python\"><span class=\"meta generic-name python\">a</span></span> <span class=\"keyword operator assignment python\">=</span> <span class=\"constant numeric integer decimal python\">1</span></span></span>\n",
+                    "<span class=\"line\"><span class=\"line-number\" aria-hidden=\"true\">2</span><span class=\"source python\"><span class=\"meta qualified-name python\"><span class=\"meta generic-name python\">b</span></span> <span class=\"keyword operator assignment python\">=</span> <span class=\"constant numeric integer decimal python\">2</span></span></span>\n",
+                    "</code></pre>\n",
+                ),
+            ),
+            (
+                indoc! {r#"
+                    ```python file="src/<app>.py"
+                    a = 1
+                    ```
+                "#},
+                concat!(
+                    "<figure class=\"code-block\">",
+                    "<figcaption class=\"code-file\">src/&lt;app&gt;.py</figcaption>",
+                    "<pre class=\"language-python\"><code>",
+                    "<span class=\"line\"><span class=\"source python\"><span class=\"meta qualified-name python\"><span class=\"meta generic-name python\">a</span></span> <span class=\"keyword operator assignment python\">=</span> <span class=\"constant numeric integer decimal python\">1</span></span></span>\n",
+                    "</code></pre></figure>\n",
+                ),
+            ),
+            (
+                indoc! {"
+                    ```python {2} showLineNumbers
+                    a = 1
+                    b = 2
+                    ```
+                "},
+                concat!(
+                    "<pre class=\"language-python line-numbers\"><code>",
+                    "<span class=\"line\"><span class=\"line-number\" aria-hidden=\"true\">1</span><span class=\"source python\"><span class=\"meta qualified-name python\"><span class=\"meta generic-name python\">a</span></span> <span class=\"keyword operator assignment python\">=</span> <span class=\"constant numeric integer decimal python\">1</span></span></span>\n",
+                    "<span class=\"line highlighted\"><span class=\"line-number\" aria-hidden=\"true\">2</span><span class=\"source python\"><span class=\"meta qualified-name python\"><span class=\"meta generic-name python\">b</span></span> <span class=\"keyword operator assignment python\">=</span> <span class=\"constant numeric integer decimal python\">2</span></span></span>\n",
+                    "</code></pre>\n",
+                ),
+            ),
+        ];
+
+        test_markdown_produces_expected_html(cases);
+    }
+
+    #[test]
+    fn diagrams() {
+        let md = indoc! {"
+            ```mermaid showLineNumbers
+            graph TD
+                A --> B[\"<b>\"]
+            ```
+        "};
+
+        test_markdown_produces_expected_html(vec![(
+            md,
+            "<pre class=\"mermaid\">graph TD\n    A --&gt; B[&quot;&lt;b&gt;&quot;]\n</pre>\n",
+        )]);
+
+        assert!(has_diagrams(&ast(md)));
+        assert!(!has_diagrams(&ast("```python\nx = 1\n```\n")));
+    }
+
+    #[test]
+    fn unknown_language_fallback() {
+        let md = indoc! {"
+            ```jdsl showLineNumbers
+            if x < 1 {
+            ```
+
+            ```
+            <plain>
+            ```
+
+            ```mermaid
+            graph TD
+            ```
+        "};
+
+        test_markdown_produces_expected_html(vec![(
+            md,
+            concat!(
+                "<pre class=\"language-jdsl line-numbers\"><code>",
+                "<span class=\"line\"><span class=\"line-number\" aria-hidden=\"true\">1</span>if x &lt; 1 {</span>\n",
+                "</code></pre>\n",
+                "<pre class=\"language-text\"><code>",
+                "<span class=\"line\">&lt;plain&gt;</span>\n",
+                "</code></pre>\n",
+                "<pre class=\"mermaid\">graph TD\n</pre>\n",
+            ),
+        )]);
+
+        assert_eq!(
+            unknown_languages(&ast(md), md),
+            vec![UnknownLanguage {
+                language: String::from("jdsl"),
+                line: Some(1),
+            }]
+        );
+        assert!(unknown_languages(&ast("```rust\nfn main() {}\n```\n"), "").is_empty());
+    }
 }
@@ -0,0 +1,300 @@
+use markdown_it::{
+    parser::{
+        block::{BlockRule, BlockState},
+        inline::InlineRoot,
+    },
+    plugins::cmark::block::paragraph::{Paragraph, ParagraphScanner},
+    MarkdownIt, Node, NodeValue, Renderer,
+};
+
+use super::admonition::{content_after_marker, tokenize_hanging};
+
+// A term on a line of its own followed by one or more definitions starting with `:`, e.g.
+//
+//   Apple
+//   : A fruit
+#[derive(Debug)]
+struct DefinitionList;
+
+impl NodeValue for DefinitionList {
+    fn render(&self, node: &Node, fmt: &mut dyn Renderer) {
+        fmt.cr();
+        fmt.open("dl", &node.attrs);
+        fmt.cr();
+        fmt.contents(&node.children);
+        fmt.cr();
+        fmt.close("dl");
+        fmt.cr();
+    }
+}
+
+#[derive(Debug)]
+struct DefinitionTerm;
+
+impl NodeValue for DefinitionTerm {
+    fn render(&self, node: &Node, fmt: &mut dyn Renderer) {
+        fmt.cr();
+        fmt.open("dt", &node.attrs);
+        fmt.contents(&node.children);
+        fmt.close("dt");
+        fmt.cr();
+    }
+}
+
+#[derive(Debug)]
+struct DefinitionDetails;
+
+impl NodeValue for DefinitionDetails {
+    fn render(&self, node: &Node, fmt: &mut dyn Renderer) {
+        fmt.cr();
+        fmt.open("dd", &node.attrs);
+        fmt.contents(&node.children);
+        fmt.close("dd");
+        fmt.cr();
+    }
+}
+
+struct DefinitionListScanner;
+
+impl DefinitionListScanner {
+    // Where the content of the definition starting on `line` begins and how far it's indented
+    fn definition(state: &BlockState, line: usize) -> Option<(usize, i32)> {
+        if line >= state.line_max || state.line_indent(line) >= state.md.max_indent {
+            return None;
+        }
+
+        let rest = state.get_line(line).strip_prefix(':')?;
+
+        if !rest.starts_with([' ', '\t']) {
+            return None;
+        }
+
+        Some(content_after_marker(state, line, 1))
+    }
+
+    // The line of the first definition of a term, which can be separated from it by an empty line
+    fn first_definition(state: &BlockState, term_line: usize) -> Option<usize> {
+        let is_term = term_line < state.line_max
+            && !state.is_empty(term_line)
+            && state.line_indent(term_line) >= 0
+            && state.line_indent(term_line) < state.md.max_indent
+            && Self::definition(state, term_line).is_none();
+
+        if !is_term {
+            return None;
+        }
+
+        let line = term_line + 1;
+        if line < state.line_max && state.is_empty(line) {
+            Self::definition(state, line + 1).map(|_| line + 1)
+        } else {
+            Self::definition(state, line).map(|_| line)
+        }
+    }
+
+    // Lazy continuation lines can't go past the next definition or term, which would otherwise be
+    // taken for more of the definition's text
+    fn lazy_limit(state: &BlockState, definition_line: usize) -> usize {
+        (definition_line + 1..state.line_max)
+            .find(|&line| {
+                Self::definition(state, line).is_some()
+                    || Self::first_definition(state, line).is_some()
+            })
+            .unwrap_or(state.line_max)
+    }
+
+    // Paragraphs in tight lists are rendered without `<p>` tags, as in regular lists
+    fn mark_tight(list: &mut Node) {
+        for details in list.children.iter_mut() {
+            if !details.is::<DefinitionDetails>() {
+                continue;
+            }
+
+            let children = std::mem::take(&mut details.children);
+            for mut child in children {
+                if child.is::<Paragraph>() {
+                    details.children.extend(std::mem::take(&mut child.children));
+                } else {
+                    details.children.push(child);
+                }
+            }
+        }
+    }
+}
+
+impl BlockRule for DefinitionListScanner {
+    // Terms look like paragraphs, so they can't interrupt one
+    fn check(_: &mut BlockState) -> Option<()> {
+        None
+    }
+
+    fn run(state: &mut BlockState) -> Option<(Node, usize)> {
+        let start_line = state.line;
+        let mut definition_line = Self::first_definition(state, start_line)?;
+        let mut term_line = start_line;
+        let mut list = Node::new(DefinitionList);
+        let mut tight = true;
+        let mut line;
+
+        loop {
+            let (content, mapping) =
+                state.get_lines(term_line, term_line + 1, state.blk_indent, false);
+            let mut term = Node::new(DefinitionTerm);
+            term.children
+                .push(Node::new(InlineRoot::new(content, mapping)));
+            list.children.push(term);
+
+            if definition_line > term_line + 1 {
+                tight = false;
+            }
+
+            loop {
+                let (content_start, content_indent) = Self::definition(state, definition_line)?;
+                let (details, end_line) = tokenize_hanging(
+                    state,
+                    DefinitionDetails,
+                    definition_line,
+                    content_start,
+                    content_indent,
+                    Self::lazy_limit(state, definition_line),
+                );
+
+                if (definition_line..end_line).any(|l| state.is_empty(l)) {
+                    tight = false;
+                }
+
+                list.children.push(details);
+                line = end_line;
+
+                // Another definition of the same term
+                let next_line = if line < state.line_max && state.is_empty(line) {
+                    line + 1
+                } else {
+                    line
+                };
+
+                match Self::definition(state, next_line) {
+                    Some(_) => {
+                        if next_line > line {
+                            tight = false;
+                        }
+                        definition_line = next_line;
+                    }
+                    None => break,
+                }
+            }
+
+            // Another term
+            let next_line = state.skip_empty_lines(line);
+            let same_indent = next_line < state.line_max
+                && state.line_offsets[next_line].indent_nonspace
+                    == state.line_offsets[start_line].indent_nonspace;
+
+            match Self::first_definition(state, next_line) {
+                Some(next_definition) if same_indent => {
+                    term_line = next_line;
+                    definition_line = next_definition;
+                }
+                _ => break,
+            }
+        }
+
+        if tight {
+            Self::mark_tight(&mut list);
+        }
+
+        Some((list, line - start_line))
+    }
+}
+
+pub(super) fn add_definition_list_rule(md: &mut MarkdownIt) {
+    md.block
+        .add_rule::<DefinitionListScanner>()
+        .before::<ParagraphScanner>();
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::tests::test_markdown_produces_expected_html;
+
+    #[test]
+    fn definition_lists() {
+        let cases: Vec<(&str, &str)> = vec![
+            (
+                indoc! {"
+                    Apple
+                    : A *fruit*
+                    : A company
+
+                    Rust
+                    : A language
+                "},
+                indoc! {"
+                    <dl>
+                    <dt>Apple</dt>
+                    <dd>A <em>fruit</em></dd>
+                    <dd>A company</dd>
+                    <dt>Rust</dt>
+                    <dd>A language</dd>
+                    </dl>
+                "},
+            ),
+            (
+                indoc! {"
+                    Term
+
+                    : First paragraph
+
+                      Second paragraph
+
+                    After the list.
+                "},
+                indoc! {"
+                    <dl>
+                    <dt>Term</dt>
+                    <dd>
+                    <p>First paragraph</p>
+                    <p>Second paragraph</p>
+                    </dd>
+                    </dl>
+                    <p>After the list.</p>
+                "},
+            ),
+            (
+                "A paragraph\nTerm\n: Not a definition\n",
+                "<p>A paragraph\nTerm\n: Not a definition</p>\n",
+            ),
+            (":no space\n", "<p>:no space</p>\n"),
+            (
+                indoc! {"
+                    Term
+                    :   definition
+                    continuing here.
+
+                    Next term
+                    :   Its definition
+                    Not a continuation
+                    :   But a term
+
+                    After the list.
+                "},
+                indoc! {"
+                    <dl>
+                    <dt>Term</dt>
+                    <dd>definition
+                    continuing here.</dd>
+                    <dt>Next term</dt>
+                    <dd>Its definition</dd>
+                    <dt>Not a continuation</dt>
+                    <dd>But a term</dd>
+                    </dl>
+                    <p>After the list.</p>
+                "},
+            ),
+        ];
+
+        test_markdown_produces_expected_html(cases);
+    }
+}
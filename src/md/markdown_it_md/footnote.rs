@@ -0,0 +1,394 @@
+use std::collections::HashMap;
+
+use markdown_it::{
+    parser::{
+        block::{BlockRule, BlockState},
+        core::CoreRule,
+        inline::{InlineRule, InlineState},
+    },
+    plugins::cmark::block::{paragraph::Paragraph, reference::ReferenceScanner},
+    MarkdownIt, Node, NodeValue, Renderer,
+};
+
+use super::admonition::{content_after_marker, tokenize_hanging};
+
+const CONTENT_INDENT: i32 = 4;
+
+// `[^label]`, numbered in the order in which footnotes are first referenced
+#[derive(Debug)]
+pub(super) struct FootnoteReference {
+    label: String,
+    // None when there's no footnote with the label, in which case the reference stays text
+    number: Option<usize>,
+    // Footnotes can be referenced more than once and each reference gets its own back-reference
+    occurrence: usize,
+}
+
+fn reference_id(number: usize, occurrence: usize) -> String {
+    if occurrence > 1 {
+        format!("fnref-{number}-{occurrence}")
+    } else {
+        format!("fnref-{number}")
+    }
+}
+
+impl NodeValue for FootnoteReference {
+    fn render(&self, _: &Node, fmt: &mut dyn Renderer) {
+        let Some(number) = self.number else {
+            fmt.text(&format!("[^{}]", self.label));
+            return;
+        };
+
+        let attrs = vec![
+            ("href", format!("#fn-{number}")),
+            ("id", reference_id(number, self.occurrence)),
+            ("role", String::from("doc-noteref")),
+        ];
+
+        fmt.open("sup", &[("class", String::from("footnote-ref"))]);
+        fmt.open("a", &attrs);
+        fmt.text(&number.to_string());
+        fmt.close("a");
+        fmt.close("sup");
+    }
+}
+
+// `[^label]: text`, with any further paragraphs indented by four spaces. Definitions are moved to
+// the end of the page.
+#[derive(Debug)]
+struct FootnoteDefinition {
+    label: String,
+}
+
+impl NodeValue for FootnoteDefinition {
+    fn render(&self, node: &Node, fmt: &mut dyn Renderer) {
+        fmt.contents(&node.children);
+    }
+}
+
+#[derive(Debug)]
+struct Footnotes;
+
+impl NodeValue for Footnotes {
+    fn render(&self, node: &Node, fmt: &mut dyn Renderer) {
+        fmt.cr();
+        fmt.open(
+            "section",
+            &[
+                ("class", String::from("footnotes")),
+                ("role", String::from("doc-endnotes")),
+            ],
+        );
+        fmt.cr();
+        fmt.self_close("hr", &[]);
+        fmt.cr();
+        fmt.open("ol", &[]);
+        fmt.cr();
+        fmt.contents(&node.children);
+        fmt.cr();
+        fmt.close("ol");
+        fmt.cr();
+        fmt.close("section");
+        fmt.cr();
+    }
+}
+
+#[derive(Debug)]
+struct Footnote {
+    number: usize,
+}
+
+impl NodeValue for Footnote {
+    fn render(&self, node: &Node, fmt: &mut dyn Renderer) {
+        fmt.open("li", &[("id", format!("fn-{}", self.number))]);
+        fmt.contents(&node.children);
+        fmt.close("li");
+        fmt.cr();
+    }
+}
+
+// Links back to each of the references to a footnote
+#[derive(Debug)]
+struct FootnoteBackrefs {
+    number: usize,
+    count: usize,
+}
+
+impl NodeValue for FootnoteBackrefs {
+    fn render(&self, _: &Node, fmt: &mut dyn Renderer) {
+        for occurrence in 1..=self.count {
+            let attrs = vec![
+                (
+                    "href",
+                    format!("#{}", reference_id(self.number, occurrence)),
+                ),
+                ("class", String::from("footnote-backref")),
+                ("role", String::from("doc-backlink")),
+                ("aria-label", format!("Back to reference {}", self.number)),
+            ];
+
+            fmt.text(" ");
+            fmt.open("a", &attrs);
+            // Text presentation so that the arrow doesn't turn into an emoji
+            fmt.text("↩\u{fe0e}");
+            if occurrence > 1 {
+                fmt.open("sup", &[]);
+                fmt.text(&occurrence.to_string());
+                fmt.close("sup");
+            }
+            fmt.close("a");
+        }
+    }
+}
+
+fn parse_label(s: &str) -> Option<&str> {
+    let label = &s[..s.find(']')?];
+
+    if label.is_empty() || label.contains(|c: char| c.is_whitespace() || c == '[') {
+        None
+    } else {
+        Some(label)
+    }
+}
+
+struct FootnoteReferenceScanner;
+
+impl InlineRule for FootnoteReferenceScanner {
+    const MARKER: char = '[';
+
+    fn run(state: &mut InlineState) -> Option<(Node, usize)> {
+        let rest = state.src[state.pos..state.pos_max].strip_prefix("[^")?;
+        let label = parse_label(rest)?;
+
+        let node = Node::new(FootnoteReference {
+            label: String::from(label),
+            number: None,
+            occurrence: 0,
+        });
+
+        Some((node, label.len() + 3))
+    }
+}
+
+struct FootnoteDefinitionScanner;
+
+impl FootnoteDefinitionScanner {
+    // The label and the length of the `[^label]:` marker
+    fn marker(line: &str) -> Option<(&str, usize)> {
+        let rest = line.strip_prefix("[^")?;
+        let label = parse_label(rest)?;
+
+        rest[label.len()..]
+            .starts_with("]:")
+            .then_some((label, label.len() + 4))
+    }
+}
+
+impl BlockRule for FootnoteDefinitionScanner {
+    fn check(state: &mut BlockState) -> Option<()> {
+        if state.line_indent(state.line) >= state.md.max_indent {
+            return None;
+        }
+
+        Self::marker(state.get_line(state.line)).map(|_| ())
+    }
+
+    fn run(state: &mut BlockState) -> Option<(Node, usize)> {
+        Self::check(state)?;
+
+        let start_line = state.line;
+        let (label, marker_len) = Self::marker(state.get_line(start_line))?;
+        let label = String::from(label);
+
+        let (content_start, _) = content_after_marker(state, start_line, marker_len);
+        let content_indent = state.line_offsets[start_line].indent_nonspace + CONTENT_INDENT;
+
+        let (node, end_line) = tokenize_hanging(
+            state,
+            FootnoteDefinition { label },
+            start_line,
+            content_start,
+            content_indent,
+            state.line_max,
+        );
+
+        Some((node, end_line - start_line))
+    }
+}
+
+fn take_definitions(root: &mut Node) -> Vec<Node> {
+    let mut definitions: Vec<Node> = Vec::new();
+
+    root.walk_mut(|node, _| {
+        let mut idx = 0;
+        while idx < node.children.len() {
+            if node.children[idx].is::<FootnoteDefinition>() {
+                definitions.push(node.children.remove(idx));
+            } else {
+                idx += 1;
+            }
+        }
+    });
+
+    definitions
+}
+
+fn label_of(node: &Node) -> &str {
+    node.cast::<FootnoteDefinition>()
+        .map_or("", |definition| definition.label.as_str())
+}
+
+// Numbers the references to footnotes that exist, adding newly referenced labels to `order`
+fn number_references(
+    node: &mut Node,
+    definitions: &[Node],
+    order: &mut Vec<String>,
+    counts: &mut HashMap<String, usize>,
+) {
+    node.walk_mut(|node, _| {
+        let Some(reference) = node.cast_mut::<FootnoteReference>() else {
+            return;
+        };
+
+        if !definitions.iter().any(|d| label_of(d) == reference.label) {
+            return;
+        }
+
+        let number = match order.iter().position(|label| *label == reference.label) {
+            Some(idx) => idx + 1,
+            None => {
+                order.push(reference.label.clone());
+                order.len()
+            }
+        };
+        let count = counts.entry(reference.label.clone()).or_default();
+        *count += 1;
+
+        reference.number = Some(number);
+        reference.occurrence = *count;
+    });
+}
+
+struct FootnotesRule;
+
+impl CoreRule for FootnotesRule {
+    fn run(root: &mut Node, _: &MarkdownIt) {
+        let mut definitions = take_definitions(root);
+        let mut order: Vec<String> = Vec::new();
+        let mut counts: HashMap<String, usize> = HashMap::new();
+
+        number_references(root, &definitions, &mut order, &mut counts);
+
+        // Footnotes can reference other footnotes
+        let mut footnotes = Node::new(Footnotes);
+        let mut idx = 0;
+
+        while idx < order.len() {
+            let number = idx + 1;
+            let Some(position) = definitions.iter().position(|d| label_of(d) == order[idx]) else {
+                break;
+            };
+            let mut definition = definitions.remove(position);
+
+            number_references(&mut definition, &definitions, &mut order, &mut counts);
+
+            let mut footnote = Node::new(Footnote { number });
+            footnote.children = std::mem::take(&mut definition.children);
+
+            // The back-references go at the end of the last paragraph if there is one
+            let backrefs = Node::new(FootnoteBackrefs {
+                number,
+                count: counts.get(&order[idx]).copied().unwrap_or(1),
+            });
+            match footnote.children.last_mut() {
+                Some(last) if last.is::<Paragraph>() => last.children.push(backrefs),
+                _ => footnote.children.push(backrefs),
+            }
+
+            footnotes.children.push(footnote);
+            idx += 1;
+        }
+
+        if !footnotes.children.is_empty() {
+            root.children.push(footnotes);
+        }
+    }
+}
+
+pub(super) fn add_footnote_rules(md: &mut MarkdownIt) {
+    md.inline
+        .add_rule::<FootnoteReferenceScanner>()
+        .before_all();
+    md.block
+        .add_rule::<FootnoteDefinitionScanner>()
+        .before::<ReferenceScanner>();
+    md.add_rule::<FootnotesRule>();
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::tests::test_markdown_produces_expected_html;
+
+    #[test]
+    fn footnotes() {
+        let cases: Vec<(&str, &str)> = vec![
+            (
+                indoc! {"
+                    Some text[^note] and more[^1], then the note again[^note] and a [^missing] one.
+
+                    [^1]: The first footnote.
+
+                    [^note]: Defined first but referenced later.
+
+                        With a second paragraph.
+
+                    [^unused]: Never referenced.
+                "},
+                indoc! {r##"
+                    <p>Some text<sup class="footnote-ref"><a href="#fn-1" id="fnref-1" role="doc-noteref">1</a></sup> and more<sup class="footnote-ref"><a href="#fn-2" id="fnref-2" role="doc-noteref">2</a></sup>, then the note again<sup class="footnote-ref"><a href="#fn-1" id="fnref-1-2" role="doc-noteref">1</a></sup> and a [^missing] one.</p>
+                    <section class="footnotes" role="doc-endnotes">
+                    <hr>
+                    <ol>
+                    <li id="fn-1">
+                    <p>Defined first but referenced later.</p>
+                    <p>With a second paragraph. <a href="#fnref-1" class="footnote-backref" role="doc-backlink" aria-label="Back to reference 1">↩︎</a> <a href="#fnref-1-2" class="footnote-backref" role="doc-backlink" aria-label="Back to reference 1">↩︎<sup>2</sup></a></p>
+                    </li>
+                    <li id="fn-2">
+                    <p>The first footnote. <a href="#fnref-2" class="footnote-backref" role="doc-backlink" aria-label="Back to reference 2">↩︎</a></p>
+                    </li>
+                    </ol>
+                    </section>
+                "##},
+            ),
+            ("No footnotes[^x].", "<p>No footnotes[^x].</p>\n"),
+            (
+                indoc! {"
+                    A claim[^1].
+
+                    [^1]: A long note
+                    that wraps lazily.
+                    [^2]: Not referenced.
+
+                    After the notes.
+                "},
+                indoc! {r##"
+                    <p>A claim<sup class="footnote-ref"><a href="#fn-1" id="fnref-1" role="doc-noteref">1</a></sup>.</p>
+                    <p>After the notes.</p>
+                    <section class="footnotes" role="doc-endnotes">
+                    <hr>
+                    <ol>
+                    <li id="fn-1">
+                    <p>A long note
+                    that wraps lazily. <a href="#fnref-1" class="footnote-backref" role="doc-backlink" aria-label="Back to reference 1">↩︎</a></p>
+                    </li>
+                    </ol>
+                    </section>
+                "##},
+            ),
+        ];
+
+        test_markdown_produces_expected_html(cases);
+    }
+}
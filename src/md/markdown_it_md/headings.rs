@@ -1,8 +1,10 @@
-use std::vec::IntoIter;
+use std::{collections::HashSet, vec::IntoIter};
 
 use markdown_it::{
     parser::{
         block::{BlockRule, BlockState},
+        core::CoreRule,
+        extset::MarkdownItExt,
         inline::InlineRoot,
     },
     MarkdownIt, Node, NodeValue, Renderer,
@@ -10,30 +12,20 @@ use markdown_it::{
 use serde::Serialize;
 use slug::slugify;
 
-use super::parse::nodes_to_string;
+use crate::config::{AnchorPosition, HeadingAnchorsConfig};
+
+use super::{links::line_at, parse::nodes_to_string};
+
+// For headings whose text doesn't leave anything to make a slug from, like `## ?!`
+const FALLBACK_SLUG: &str = "section";
 
 #[derive(Debug)]
 pub(super) struct FancyHeading {
     pub(super) level: u8,
-}
-
-fn h_attrs<'a>(slug: &str) -> Vec<(&'a str, String)> {
-    vec![
-        ("id", String::from(slug)),
-        // For AlpineJS
-        ("x-data", String::from("{ open: false }")),
-        ("@mouseover", String::from("open = true")),
-        ("@mouseout", String::from("open = false")),
-    ]
-}
-
-fn a_attrs<'a>(slug: &str) -> Vec<(&'a str, String)> {
-    vec![
-        ("href", format!("#{}", slug)),
-        ("x-show", String::from("open")),
-        ("x-transition.duration.200ms", String::from("")),
-        ("class", String::from("ml-2 text-primary not-prose")),
-    ]
+    // Set with `{#custom-id}` at the end of the heading
+    custom_id: Option<String>,
+    // Unique on the page and shared by the HTML, the table of contents, and the search index
+    pub(super) slug: String,
 }
 
 impl NodeValue for FancyHeading {
@@ -45,22 +37,61 @@ impl NodeValue for FancyHeading {
         static TAG: [&str; 5] = ["h2", "h3", "h4", "h5", "h6"];
         debug_assert!(self.level >= 2 && self.level <= 6);
 
-        // Add slug to attributes
-        let slug = slugify(node.collect_text());
-        let h_attrs = h_attrs(&slug);
-        let a_attrs = a_attrs(&slug);
-
         fmt.cr();
-        fmt.open(TAG[self.level as usize - 2], &h_attrs);
+        fmt.open(TAG[self.level as usize - 2], &[("id", self.slug.clone())]);
         fmt.contents(&node.children);
-        fmt.open("a", &a_attrs);
-        fmt.text("#");
-        fmt.close("a");
         fmt.close(TAG[self.level as usize - 2]);
         fmt.cr();
     }
 }
 
+impl MarkdownItExt for HeadingAnchorsConfig {}
+
+// Added to headings once their slugs are known. It has no text of its own so that the symbol
+// doesn't end up in the table of contents or the search index.
+#[derive(Debug)]
+struct HeadingAnchor {
+    slug: String,
+    symbol: String,
+    class: String,
+}
+
+impl NodeValue for HeadingAnchor {
+    fn render(&self, _: &Node, fmt: &mut dyn Renderer) {
+        let attrs = vec![
+            ("href", format!("#{}", self.slug)),
+            ("class", self.class.clone()),
+        ];
+
+        fmt.open("a", &attrs);
+        fmt.text(&self.symbol);
+        fmt.close("a");
+    }
+}
+
+// `{#custom-id}` at the end of a heading's text, preceded by whitespace
+fn split_custom_id(content: &str) -> (&str, Option<String>) {
+    let trimmed = content.trim_end();
+
+    let Some(start) = trimmed.rfind("{#") else {
+        return (content, None);
+    };
+    let Some(id) = trimmed[start + 2..].strip_suffix('}') else {
+        return (content, None);
+    };
+
+    let text = &trimmed[..start];
+    let valid = !id.is_empty()
+        && !id.contains(|c: char| c.is_whitespace() || c == '{' || c == '}')
+        && (text.is_empty() || text.ends_with([' ', '\t']));
+
+    if valid {
+        (text.trim_end(), Some(String::from(id)))
+    } else {
+        (content, None)
+    }
+}
+
 struct FancyHeadingsRule;
 
 impl BlockRule for FancyHeadingsRule {
@@ -114,34 +145,157 @@ impl BlockRule for FancyHeadingsRule {
             None => text_pos,
         };
 
-        let content = line[text_pos..text_max].to_owned();
+        let (content, custom_id) = split_custom_id(&line[text_pos..text_max]);
+        let content = content.to_owned();
         let mapping = vec![(0, state.line_offsets[state.line].first_nonspace + text_pos)];
 
-        let mut node = Node::new(FancyHeading { level });
+        let mut node = Node::new(FancyHeading {
+            level,
+            custom_id,
+            slug: String::new(),
+        });
         node.children
             .push(Node::new(InlineRoot::new(content, mapping)));
         Some((node, 1))
     }
 }
 
-pub(super) fn add_heading_rule(md: &mut MarkdownIt) {
+// Custom IDs are kept as they are and slugs made from the text get a numeric suffix when they're
+// already taken, e.g. `examples` and `examples-1`. h1s aren't rendered, so they don't get one.
+struct HeadingSlugsRule;
+
+impl CoreRule for HeadingSlugsRule {
+    fn run(root: &mut Node, md: &MarkdownIt) {
+        let anchors = md
+            .ext
+            .get::<HeadingAnchorsConfig>()
+            .cloned()
+            .unwrap_or_default();
+        let mut taken: HashSet<String> = HashSet::new();
+
+        root.walk(|node, _| {
+            if let Some(FancyHeading {
+                level: 2..,
+                custom_id: Some(id),
+                ..
+            }) = node.cast::<FancyHeading>()
+            {
+                taken.insert(id.clone());
+            }
+        });
+
+        root.walk_mut(|node, _| {
+            if !node.is::<FancyHeading>() {
+                return;
+            }
+
+            let text = node.collect_text();
+            let Some(heading) = node.cast_mut::<FancyHeading>() else {
+                return;
+            };
+
+            if heading.level == 1 {
+                return;
+            }
+
+            heading.slug = match &heading.custom_id {
+                Some(id) => id.clone(),
+                None => {
+                    let mut base = slugify(text);
+                    if base.is_empty() {
+                        base = String::from(FALLBACK_SLUG);
+                    }
+                    let mut slug = base.clone();
+                    let mut suffix = 1;
+                    while taken.contains(&slug) {
+                        slug = format!("{base}-{suffix}");
+                        suffix += 1;
+                    }
+
+                    taken.insert(slug.clone());
+                    slug
+                }
+            };
+
+            if !anchors.enabled {
+                return;
+            }
+
+            let anchor = Node::new(HeadingAnchor {
+                slug: heading.slug.clone(),
+                symbol: anchors.symbol.clone(),
+                class: anchors.class.clone(),
+            });
+            match anchors.position {
+                AnchorPosition::Before => node.children.insert(0, anchor),
+                AnchorPosition::After => node.children.push(anchor),
+            }
+        });
+    }
+}
+
+// A custom ID that an earlier heading on the page already has, with its line in the Markdown
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) struct DuplicateId {
+    pub(crate) id: String,
+    pub(crate) line: Option<usize>,
+}
+
+pub(crate) fn duplicate_heading_ids(root: &Node, source: &str) -> Vec<DuplicateId> {
+    let mut seen: HashSet<&str> = HashSet::new();
+    let mut duplicates: Vec<DuplicateId> = Vec::new();
+
+    root.walk(|node, _| {
+        let Some(FancyHeading {
+            level: 2..,
+            custom_id: Some(id),
+            ..
+        }) = node.cast::<FancyHeading>()
+        else {
+            return;
+        };
+
+        if !seen.insert(id) {
+            duplicates.push(DuplicateId {
+                id: id.clone(),
+                line: node
+                    .srcmap
+                    .map(|pos| line_at(source, pos.get_byte_offsets().0)),
+            });
+        }
+    });
+
+    duplicates
+}
+
+pub(super) fn add_heading_rule(md: &mut MarkdownIt, anchors: &HeadingAnchorsConfig) {
+    md.ext.insert(anchors.clone());
     md.block.add_rule::<FancyHeadingsRule>();
+    md.add_rule::<HeadingSlugsRule>();
 }
 
 #[derive(Debug, Eq, PartialEq, Serialize)]
 pub(super) struct Heading {
     pub(super) level: u8,
     pub(super) text: String,
-    slug: String,
+    pub(super) slug: String,
 }
 
 impl Heading {
+    fn from_node(heading: &FancyHeading, node: &Node) -> Self {
+        Self {
+            level: heading.level,
+            text: node.collect_text(),
+            slug: heading.slug.clone(),
+        }
+    }
+
+    #[cfg(test)]
     fn new(level: u8, text: &str) -> Self {
-        let slug = slugify(text);
         Self {
             level,
             text: String::from(text),
-            slug,
+            slug: slugify(text),
         }
     }
 }
@@ -158,7 +312,7 @@ impl<'a> IntoIterator for Headings<'a> {
         for node in self.0.iter() {
             if let Some(heading) = node.cast::<FancyHeading>() {
                 if heading.level > 1 {
-                    headings.push(Heading::new(heading.level, &node.collect_text()));
+                    headings.push(Heading::from_node(heading, node));
                 }
             }
         }
@@ -179,7 +333,7 @@ impl<'a> IntoIterator for HeadingsWithIdx<'a> {
         for (idx, node) in self.0.iter().enumerate() {
             if let Some(heading) = node.cast::<FancyHeading>() {
                 if heading.level > 1 {
-                    headings.push((idx, Heading::new(heading.level, &node.collect_text())));
+                    headings.push((idx, Heading::from_node(heading, node)));
                 }
             }
         }
@@ -202,7 +356,7 @@ impl<'a> IntoIterator for HeadingsWithTextAfter<'a> {
                 if heading.level > 1 {
                     let next = idx + 1;
 
-                    let heading = Heading::new(heading.level, &node.collect_text());
+                    let heading = Heading::from_node(heading, node);
 
                     match self.0.get(next) {
                         Some(next_node) => {
@@ -241,9 +395,13 @@ impl<'a> IntoIterator for HeadingsWithTextAfter<'a> {
 mod tests {
     use indoc::indoc;
 
-    use crate::{md::ast, tests::test_markdown_produces_expected_html};
+    use crate::{
+        config::{AnchorPosition, HeadingAnchorsConfig, MarkdownConfig},
+        md::{ast, ast_with_config, render, TableOfContents},
+        tests::test_markdown_produces_expected_html,
+    };
 
-    use super::{Heading, Headings, HeadingsWithTextAfter};
+    use super::{duplicate_heading_ids, DuplicateId, Heading, Headings, HeadingsWithTextAfter};
 
     #[test]
     fn search_index() {
@@ -291,17 +449,131 @@ mod tests {
         let cases: Vec<(&str, &str)> = vec![
             (
                 "## Hello world",
-                "<h2 id=\"hello-world\" x-data=\"{ open: false }\" @mouseover=\"open = true\" @mouseout=\"open = false\">Hello world<a href=\"#hello-world\" x-show=\"open\" x-transition.duration.200ms=\"\" class=\"ml-2 text-primary not-prose\">#</a></h2>\n",
+                "<h2 id=\"hello-world\">Hello world<a href=\"#hello-world\" class=\"heading-anchor\">#</a></h2>\n",
             ),
             (
                 "### A heading with some `code`",
-                "<h3 id=\"a-heading-with-some-code\" x-data=\"{ open: false }\" @mouseover=\"open = true\" @mouseout=\"open = false\">A heading with some <code>code</code><a href=\"#a-heading-with-some-code\" x-show=\"open\" x-transition.duration.200ms=\"\" class=\"ml-2 text-primary not-prose\">#</a></h3>\n",
+                "<h3 id=\"a-heading-with-some-code\">A heading with some <code>code</code><a href=\"#a-heading-with-some-code\" class=\"heading-anchor\">#</a></h3>\n",
             ),
         ];
 
         test_markdown_produces_expected_html(cases);
     }
 
+    #[test]
+    fn configured_anchors() {
+        let cases: Vec<(HeadingAnchorsConfig, &str)> = vec![
+            (
+                HeadingAnchorsConfig {
+                    symbol: String::from("¶"),
+                    class: String::from("anchor"),
+                    position: AnchorPosition::Before,
+                    ..Default::default()
+                },
+                "<h2 id=\"setup\"><a href=\"#setup\" class=\"anchor\">¶</a>Setup</h2>\n",
+            ),
+            (
+                HeadingAnchorsConfig {
+                    enabled: false,
+                    ..Default::default()
+                },
+                "<h2 id=\"setup\">Setup</h2>\n",
+            ),
+        ];
+
+        for (heading_anchors, expected) in cases {
+            let config = MarkdownConfig {
+                heading_anchors,
+                ..Default::default()
+            };
+            let tree = ast_with_config("## Setup", &config, &Default::default());
+
+            assert_eq!(render(&tree), expected);
+            assert_eq!(
+                Headings(&tree.children).into_iter().next().unwrap().text,
+                "Setup"
+            );
+        }
+    }
+
+    #[test]
+    fn unique_slugs() {
+        let tree = ast(indoc! {"
+            ## Examples
+
+            ### Examples
+
+            ## Reworded heading {#stable}
+
+            ## More examples {#examples-1}
+
+            ## Not an {#id with spaces}
+
+            ## Needs{#whitespace}
+        "});
+        let expected = vec![
+            "examples",
+            "examples-2",
+            "stable",
+            "examples-1",
+            "not-an-id-with-spaces",
+            "needs-whitespace",
+        ];
+
+        let html = render(&tree);
+        let ids: Vec<&str> = html
+            .split(" id=\"")
+            .skip(1)
+            .filter_map(|s| s.split('"').next())
+            .collect();
+        assert_eq!(ids, expected);
+
+        assert_eq!(TableOfContents::parse(&tree).slugs(), expected);
+
+        let search: Vec<String> = HeadingsWithTextAfter(&tree.children)
+            .into_iter()
+            .map(|(heading, _)| heading.slug)
+            .collect();
+        assert_eq!(search, expected);
+
+        assert!(html.contains(">Reworded heading<a href=\"#stable\""));
+        assert!(html.contains(">Not an {#id with spaces}<a"));
+    }
+
+    #[test]
+    fn slugs_that_need_help() {
+        let md = indoc! {"
+            # Setup
+
+            ## Setup
+
+            ## {#}
+
+            ## ?!
+
+            ## Twice {#a}
+
+            ## Again {#a}
+        "};
+        let tree = ast(md);
+
+        let html = render(&tree);
+        let ids: Vec<&str> = html
+            .split(" id=\"")
+            .skip(1)
+            .filter_map(|s| s.split('"').next())
+            .collect();
+        assert_eq!(ids, vec!["setup", "section", "section-1", "a", "a"]);
+
+        assert_eq!(
+            duplicate_heading_ids(&tree, md),
+            vec![DuplicateId {
+                id: String::from("a"),
+                line: Some(11),
+            }]
+        );
+    }
+
     #[test]
     fn md_to_headings() {
         let cases: Vec<(&str, Vec<Heading>)> = vec![
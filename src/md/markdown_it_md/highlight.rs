@@ -1,41 +1,453 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::Path,
+    sync::{Arc, OnceLock},
+};
+
+use markdown_it::parser::extset::MarkdownItExt;
 use syntect::{
-    html::{ClassStyle, ClassedHTMLGenerator},
-    parsing::SyntaxSet,
+    easy::HighlightLines,
+    highlighting::{Color, Theme, ThemeSet},
+    html::{
+        css_for_theme_with_class_style, line_tokens_to_classed_spans,
+        styled_line_to_highlighted_html, ClassStyle, IncludeBackground,
+    },
+    parsing::{ParseState, ScopeStack, SyntaxReference, SyntaxSet},
     util::LinesWithEndings,
 };
 
-use crate::error::JellyError;
+use crate::{
+    config::{HighlightMode, HighlightingConfig, SiteConfig},
+    error::JellyError,
+};
+
+// The generated stylesheet only applies to highlighted code blocks
+const CODE_SELECTOR: &str = "pre[class*=\"language-\"]";
+
+static THEMES: OnceLock<ThemeSet> = OnceLock::new();
+static DEFAULT_SYNTAXES: OnceLock<Arc<SyntaxSet>> = OnceLock::new();
+
+fn default_syntaxes() -> Arc<SyntaxSet> {
+    DEFAULT_SYNTAXES
+        .get_or_init(|| Arc::new(SyntaxSet::load_defaults_newlines()))
+        .clone()
+}
+
+fn themes() -> &'static ThemeSet {
+    THEMES.get_or_init(ThemeSet::load_defaults)
+}
+
+fn theme(name: &str) -> Result<&'static Theme, JellyError> {
+    themes()
+        .themes
+        .get(name)
+        .ok_or_else(|| JellyError::Highlight(format!("no theme named {name:?}")))
+}
+
+// The stylesheet for highlighted code, with the dark theme taking over when the page is in dark
+// mode. There's nothing to style when the colors are inline.
+pub(crate) fn highlighting_css(config: &HighlightingConfig) -> Result<Option<String>, JellyError> {
+    if config.mode == HighlightMode::Inline {
+        return Ok(None);
+    }
+
+    let light = scoped_css(
+        theme(&config.theme)?,
+        &format!("html:not(.dark) {CODE_SELECTOR}"),
+    )?;
+    let dark = scoped_css(
+        theme(&config.dark_theme)?,
+        &format!("html.dark {CODE_SELECTOR}"),
+    )?;
 
-pub(super) struct Highlighter {
-    syntaxes: SyntaxSet,
+    Ok(Some(format!("{light}\n{dark}")))
+}
+
+// syntect's CSS styles `.code` and the scope classes anywhere on the page
+fn scoped_css(theme: &Theme, scope: &str) -> Result<String, JellyError> {
+    let css = css_for_theme_with_class_style(theme, ClassStyle::Spaced)?;
+
+    let lines: Vec<String> = css
+        .lines()
+        .map(|line| match line.strip_suffix(" {") {
+            Some(selectors) => {
+                let selectors: Vec<String> = selectors
+                    .split(", ")
+                    .map(|selector| match selector {
+                        ".code" => String::from(scope),
+                        _ => format!("{scope} {selector}"),
+                    })
+                    .collect();
+
+                format!("{} {{", selectors.join(", "))
+            }
+            None => String::from(line),
+        })
+        .collect();
+
+    Ok(lines.join("\n") + "\n")
+}
+
+fn hex(color: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
+// Building the syntax set is slow, so there's one highlighter per build that every code block
+// shares (clones are cheap)
+#[derive(Clone)]
+pub(crate) struct Highlighter {
+    syntaxes: Arc<SyntaxSet>,
+    // Other names for languages, e.g. `jsonc` for `json`
+    aliases: Arc<BTreeMap<String, String>>,
+    // Set when colors go in `style` attributes rather than coming from the stylesheet
+    inline_theme: Option<&'static Theme>,
 }
 
 impl Default for Highlighter {
     fn default() -> Self {
         Self {
-            syntaxes: SyntaxSet::load_defaults_newlines(),
+            syntaxes: default_syntaxes(),
+            aliases: Arc::default(),
+            inline_theme: None,
         }
     }
 }
 
+impl fmt::Debug for Highlighter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Highlighter")
+            .field("aliases", &self.aliases)
+            .field("inline_theme", &self.inline_theme.map(|theme| &theme.name))
+            .finish_non_exhaustive()
+    }
+}
+
+impl MarkdownItExt for Highlighter {}
+
 impl Highlighter {
-    pub(super) fn highlight(&self, language: &str, code: &str) -> Result<String, JellyError> {
-        match self.syntaxes.find_syntax_by_token(language) {
-            Some(sx) => {
-                let mut html_generator = ClassedHTMLGenerator::new_with_class_style(
-                    sx,
-                    &self.syntaxes,
-                    ClassStyle::Spaced,
-                );
-                for line in LinesWithEndings::from(code) {
-                    html_generator.parse_html_for_line_which_includes_newline(line)?;
-                }
-                Ok(html_generator.finalize())
-            }
-            None => Err(JellyError::Highlight(format!(
-                "no syntax found for language {}",
-                language
-            ))),
+    // The `.sublime-syntax` files in `syntaxes_dir` (and its subdirectories) are added to the
+    // built-in syntaxes
+    pub(crate) fn new(
+        config: &HighlightingConfig,
+        syntaxes_dir: Option<&Path>,
+    ) -> Result<Self, JellyError> {
+        let syntaxes = match syntaxes_dir {
+            Some(dir) => {
+                let mut builder = SyntaxSet::load_defaults_newlines().into_builder();
+                builder
+                    .add_from_folder(dir, true)
+                    .map_err(syntect::Error::from)?;
+                Arc::new(builder.build())
+            }
+            None => default_syntaxes(),
+        };
+
+        let inline_theme = match config.mode {
+            HighlightMode::Inline => Some(theme(&config.theme)?),
+            HighlightMode::Classes => None,
+        };
+
+        Ok(Self {
+            syntaxes,
+            aliases: Arc::new(config.aliases.clone()),
+            inline_theme,
+        })
+    }
+
+    // The site's highlighter. Syntaxes that don't load and aliases for languages that don't exist
+    // are config errors.
+    pub(crate) fn from_config(config: &SiteConfig) -> Result<Self, JellyError> {
+        let invalid = |key: &str, reason: String| {
+            JellyError::InvalidConfig(
+                config.config_file.clone().unwrap_or_default(),
+                String::from(key),
+                reason,
+            )
+        };
+
+        let highlighter = Self::new(&config.highlighting, config.syntaxes_dir.as_deref())
+            .map_err(|e| invalid("highlighting.syntaxes", e.to_string()))?;
+
+        for (alias, language) in &config.highlighting.aliases {
+            if !highlighter.has_language(language) {
+                return Err(invalid(
+                    &format!("highlighting.aliases.{alias}"),
+                    format!("{language:?} is not a known language"),
+                ));
+            }
+        }
+
+        Ok(highlighter)
+    }
+
+    fn find_syntax(&self, language: &str) -> Option<&SyntaxReference> {
+        let language = self.aliases.get(language).map_or(language, String::as_str);
+
+        self.syntaxes.find_syntax_by_token(language)
+    }
+
+    pub(crate) fn has_language(&self, language: &str) -> bool {
+        self.find_syntax(language).is_some()
+    }
+
+    // The theme's colors for the `pre` element when they're inline
+    pub(super) fn pre_style(&self) -> Option<String> {
+        let settings = &self.inline_theme?.settings;
+
+        let mut style: Vec<String> = Vec::new();
+        if let Some(background) = settings.background {
+            style.push(format!("background-color: {}", hex(background)));
+        }
+        if let Some(foreground) = settings.foreground {
+            style.push(format!("color: {}", hex(foreground)));
+        }
+
+        (!style.is_empty()).then(|| style.join("; "))
+    }
+
+    // Returns the highlighted HTML for each line of the code, without newlines. Every line is
+    // self-contained: spans that carry over from one line to the next (as in multi-line
+    // comments) are closed at the end of the line and reopened at the start of the next one,
+    // so that lines can be wrapped in their own elements.
+    pub(super) fn highlight_lines(
+        &self,
+        language: &str,
+        code: &str,
+    ) -> Result<Vec<String>, JellyError> {
+        let syntax = self.find_syntax(language).ok_or_else(|| {
+            JellyError::Highlight(format!("no syntax found for language {}", language))
+        })?;
+
+        match self.inline_theme {
+            Some(theme) => self.styled_lines(syntax, theme, code),
+            None => self.classed_lines(syntax, code),
+        }
+    }
+
+    fn classed_lines(
+        &self,
+        syntax: &SyntaxReference,
+        code: &str,
+    ) -> Result<Vec<String>, JellyError> {
+        let mut parse_state = ParseState::new(syntax);
+        let mut scope_stack = ScopeStack::new();
+        let mut lines: Vec<String> = Vec::new();
+
+        for line in LinesWithEndings::from(code) {
+            let mut html = String::new();
+            for scope in scope_stack.as_slice() {
+                html.push_str(&format!(
+                    "<span class=\"{}\">",
+                    scope.build_string().replace('.', " ")
+                ));
+            }
+
+            let ops = parse_state
+                .parse_line(line, &self.syntaxes)
+                .map_err(syntect::Error::from)?;
+            let (spans, _) =
+                line_tokens_to_classed_spans(line, &ops, ClassStyle::Spaced, &mut scope_stack)?;
+            html.push_str(&spans);
+
+            // The newline is the only one in the line and might sit inside a span
+            if let Some(newline) = html.rfind('\n') {
+                html.remove(newline);
+            }
+
+            html.push_str(&"</span>".repeat(scope_stack.len()));
+            lines.push(html);
+        }
+
+        Ok(lines)
+    }
+
+    // Styled spans never carry over from one line to the next
+    fn styled_lines(
+        &self,
+        syntax: &SyntaxReference,
+        theme: &Theme,
+        code: &str,
+    ) -> Result<Vec<String>, JellyError> {
+        let mut highlighter = HighlightLines::new(syntax, theme);
+        let mut lines: Vec<String> = Vec::new();
+
+        for line in LinesWithEndings::from(code) {
+            let ranges = highlighter.highlight_line(line, &self.syntaxes)?;
+            let mut html = styled_line_to_highlighted_html(&ranges, IncludeBackground::No)?;
+
+            if let Some(newline) = html.rfind('\n') {
+                html.remove(newline);
+            }
+
+            lines.push(html);
+        }
+
+        Ok(lines)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use indoc::indoc;
+
+    use crate::{
+        config::{HighlightMode, HighlightingConfig, SiteConfig, THEMES},
+        error::JellyError,
+    };
+
+    use super::{highlighting_css, themes, Highlighter};
+
+    #[test]
+    fn self_contained_lines() {
+        let lines = Highlighter::default()
+            .highlight_lines("rust", "/* one\ntwo */\nlet x = 1;\n")
+            .unwrap();
+
+        assert_eq!(lines.len(), 3);
+
+        for line in &lines {
+            assert_eq!(
+                line.matches("<span").count(),
+                line.matches("</span>").count(),
+                "{line}"
+            );
+            assert!(!line.contains('\n'));
+        }
+
+        assert!(lines[1].contains("comment"));
+        assert!(lines[1]
+            .starts_with("<span class=\"source rust\"><span class=\"comment block rust\">two"));
+    }
+
+    #[test]
+    fn inline_styles() {
+        let config = HighlightingConfig {
+            mode: HighlightMode::Inline,
+            ..Default::default()
+        };
+        let highlighter = Highlighter::new(&config, None).unwrap();
+        let lines = highlighter
+            .highlight_lines("rust", "/* one\ntwo */\nlet x = 1;\n")
+            .unwrap();
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[1],
+            "<span style=\"font-style:italic;color:#969896;\">two */</span>"
+        );
+        assert!(lines.iter().all(|line| !line.contains("class=")));
+        assert_eq!(
+            highlighter.pre_style().as_deref(),
+            Some("background-color: #ffffff; color: #323232")
+        );
+        assert!(Highlighter::default().pre_style().is_none());
+    }
+
+    #[test]
+    fn stylesheet() {
+        let css = highlighting_css(&HighlightingConfig::default())
+            .unwrap()
+            .unwrap();
+
+        assert!(css.contains("html:not(.dark) pre[class*=\"language-\"] {\n"));
+        assert!(css.contains("html.dark pre[class*=\"language-\"] {\n"));
+        assert!(css.contains("html.dark pre[class*=\"language-\"] .comment"));
+        // Every rule is scoped to code blocks
+        for line in css.lines().filter(|line| line.ends_with(" {")) {
+            for selector in line.trim_end_matches(" {").split(", ") {
+                assert!(selector.starts_with("html"), "{line}");
+            }
+        }
+
+        let inline = HighlightingConfig {
+            mode: HighlightMode::Inline,
+            ..Default::default()
+        };
+        assert!(highlighting_css(&inline).unwrap().is_none());
+    }
+
+    #[test]
+    fn custom_syntaxes_and_aliases() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("jelly-dsl.sublime-syntax"),
+            indoc! {r#"
+                %YAML 1.2
+                ---
+                name: Jelly DSL
+                file_extensions: [jdsl]
+                scope: source.jdsl
+                contexts:
+                  main:
+                    - match: '\b(page|section)\b'
+                      scope: keyword.control.jdsl
+            "#},
+        )
+        .unwrap();
+
+        let config = HighlightingConfig {
+            aliases: BTreeMap::from([
+                (String::from("jsonc"), String::from("json")),
+                (String::from("dsl"), String::from("jdsl")),
+            ]),
+            ..Default::default()
+        };
+        let highlighter = Highlighter::new(&config, Some(dir.path())).unwrap();
+
+        for language in ["jdsl", "dsl"] {
+            let lines = highlighter
+                .highlight_lines(language, "page home\n")
+                .unwrap();
+            assert_eq!(
+                lines[0],
+                "<span class=\"source jdsl\"><span class=\"keyword control jdsl\">page</span> home</span>"
+            );
+        }
+
+        assert!(highlighter.has_language("jsonc"));
+        assert!(highlighter.has_language("rust"));
+        assert!(!Highlighter::default().has_language("jdsl"));
+    }
+
+    #[test]
+    fn known_themes() {
+        for name in THEMES {
+            assert!(themes().themes.contains_key(name), "{name}");
+        }
+    }
+
+    #[test]
+    fn invalid_highlighting_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("jelly.yaml");
+        std::fs::create_dir(dir.path().join("syntaxes")).unwrap();
+        std::fs::write(
+            dir.path().join("syntaxes/broken.sublime-syntax"),
+            "name: Broken\ncontexts: [",
+        )
+        .unwrap();
+
+        for (yaml, expected) in [
+            (
+                "title: Foo\nhighlighting:\n  syntaxes: syntaxes",
+                "highlighting.syntaxes",
+            ),
+            (
+                "title: Foo\nhighlighting:\n  aliases:\n    jsonc: jsonnn",
+                "highlighting.aliases.jsonc",
+            ),
+        ] {
+            std::fs::write(&path, yaml).unwrap();
+            let config = SiteConfig::from_file(dir.path().to_path_buf(), &path).unwrap();
+
+            match Highlighter::from_config(&config) {
+                Err(JellyError::InvalidConfig(_, key, _)) => assert_eq!(key, expected),
+                Err(e) => panic!("unexpected error: {e}"),
+                Ok(_) => panic!("{yaml:?} should be invalid"),
+            }
         }
     }
 }
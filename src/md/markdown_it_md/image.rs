@@ -2,9 +2,11 @@ use markdown_it::{
     generics::inline::full_link, parser::inline::Text, MarkdownIt, Node, NodeValue, Renderer,
 };
 
+use crate::utils::{is_relative_url, join_url};
+
 #[derive(Debug)]
-struct FancyImage {
-    url: String,
+pub(super) struct FancyImage {
+    pub(super) url: String,
     title: Option<String>,
 }
 
@@ -34,6 +36,18 @@ impl NodeValue for FancyImage {
     }
 }
 
+// Pages are served from URLs like /setup rather than /setup/, so image paths relative to the
+// Markdown file need to be made absolute to keep working once the site is built.
+pub(crate) fn resolve_image_urls(root: &mut Node, page_dir_url: &str) {
+    root.walk_mut(|node, _| {
+        if let Some(image) = node.cast_mut::<FancyImage>() {
+            if is_relative_url(&image.url) {
+                image.url = join_url(page_dir_url, &image.url);
+            }
+        }
+    });
+}
+
 pub(super) fn add_image_rule(md: &mut MarkdownIt) {
     full_link::add_prefix::<'!', true>(md, |href, title| {
         Node::new(FancyImage {
@@ -45,7 +59,12 @@ pub(super) fn add_image_rule(md: &mut MarkdownIt) {
 
 #[cfg(test)]
 mod tests {
-    use crate::tests::test_markdown_produces_expected_html;
+    use crate::{
+        md::{ast, render},
+        tests::test_markdown_produces_expected_html,
+    };
+
+    use super::resolve_image_urls;
 
     #[test]
     fn image_render() {
@@ -59,4 +78,28 @@ mod tests {
 
         test_markdown_produces_expected_html(cases);
     }
+
+    #[test]
+    fn relative_image_urls() {
+        let cases: Vec<(&str, &str)> = vec![
+            (
+                "![](diagram.svg)",
+                "<p><figure><a href=\"/setup/diagram.svg\"><img src=\"/setup/diagram.svg\"></a></figure></p>\n",
+            ),
+            (
+                "![](../images/logo.png)",
+                "<p><figure><a href=\"/images/logo.png\"><img src=\"/images/logo.png\"></a></figure></p>\n",
+            ),
+            (
+                "![](https://example.com/foo.png)",
+                "<p><figure><a href=\"https://example.com/foo.png\"><img src=\"https://example.com/foo.png\"></a></figure></p>\n",
+            ),
+        ];
+
+        for (md, expected) in cases {
+            let mut tree = ast(md);
+            resolve_image_urls(&mut tree, "/setup");
+            assert_eq!(render(&tree), expected);
+        }
+    }
 }
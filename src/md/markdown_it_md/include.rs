@@ -0,0 +1,258 @@
+use std::path::{Path, PathBuf};
+
+use markdown_it::Node;
+
+use crate::{error::JellyError, utils::get_file};
+
+use super::{
+    annotation::annotate_code_blocks,
+    code::{FancyCodeBlock, LineRanges},
+};
+
+const ANCHOR_START: &str = "ANCHOR:";
+const ANCHOR_END: &str = "ANCHOR_END:";
+
+// Code fence attributes for pulling code in from a file, e.g.
+// ```` ```rust include="../examples/main.rs" region="setup" ````
+#[derive(Debug, Default, Eq, PartialEq)]
+pub(super) struct Include {
+    // Relative to the page's directory
+    pub(super) path: String,
+    pub(super) lines: LineRanges,
+    pub(super) region: Option<String>,
+}
+
+impl Include {
+    fn select(&self, file: &str, path: &Path, page: &Path) -> Result<String, JellyError> {
+        let lines: Vec<&str> = match &self.region {
+            Some(region) => find_region(file, region).ok_or_else(|| {
+                JellyError::MissingRegion(page.to_path_buf(), region.clone(), path.to_path_buf())
+            })?,
+            None => file.lines().collect(),
+        };
+
+        // Anchor lines for other regions aren't part of the code
+        let lines: Vec<&str> = lines
+            .into_iter()
+            .filter(|line| anchor_name(line).is_none())
+            .enumerate()
+            .filter(|(idx, _)| {
+                self.lines.is_empty() || self.lines.iter().any(|range| range.contains(&(idx + 1)))
+            })
+            .map(|(_, line)| line)
+            .collect();
+
+        Ok(dedent(&lines))
+    }
+}
+
+// Regions are delimited mdBook-style, with `ANCHOR: name` and `ANCHOR_END: name` comments
+fn find_region<'a>(file: &'a str, region: &str) -> Option<Vec<&'a str>> {
+    let mut lines = file.lines();
+
+    lines.find(|line| anchor_name(line) == Some((ANCHOR_START, region)))?;
+
+    Some(
+        lines
+            .take_while(|line| anchor_name(line) != Some((ANCHOR_END, region)))
+            .collect(),
+    )
+}
+
+fn anchor_name(line: &str) -> Option<(&'static str, &str)> {
+    // The end marker contains the start marker, so it needs to be checked first
+    [ANCHOR_END, ANCHOR_START].into_iter().find_map(|marker| {
+        let (_, rest) = line.split_once(marker)?;
+        let name = rest.split_whitespace().next()?;
+        Some((marker, name))
+    })
+}
+
+// Regions are often indented (inside a function, say) but shouldn't be in the docs
+fn dedent(lines: &[&str]) -> String {
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+
+    let mut code = String::new();
+    for line in lines {
+        code.push_str(line.get(indent..).unwrap_or("").trim_end());
+        code.push('\n');
+    }
+
+    code
+}
+
+// Fills code blocks that have an `include` attribute with the contents of the file, resolved
+// relative to the page, and picks up any annotations in it. Returns the included files so that
+// changes to them can be watched.
+pub(crate) fn include_code(root: &mut Node, page: &Path) -> Result<Vec<PathBuf>, JellyError> {
+    let page_dir = page.parent().unwrap_or(Path::new(""));
+    let mut included: Vec<PathBuf> = Vec::new();
+    let mut result: Result<(), JellyError> = Ok(());
+
+    root.walk_mut(|node, _| {
+        if result.is_err() {
+            return;
+        }
+
+        let Some(code) = node.cast_mut::<FancyCodeBlock>() else {
+            return;
+        };
+        let Some(include) = code.include() else {
+            return;
+        };
+
+        let path = page_dir.join(&include.path);
+        let content = if path.is_file() {
+            get_file(&path).and_then(|file| include.select(&file, &path, page))
+        } else {
+            Err(JellyError::IncludeNotFound(
+                page.to_path_buf(),
+                path.clone(),
+            ))
+        };
+
+        match content {
+            Ok(content) => code.set_included_content(content, &path),
+            Err(e) => result = Err(e),
+        }
+
+        included.push(path);
+    });
+
+    result?;
+
+    if !included.is_empty() {
+        annotate_code_blocks(root);
+    }
+
+    Ok(included)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use indoc::{formatdoc, indoc};
+
+    use crate::{
+        error::JellyError,
+        md::{ast, render},
+    };
+
+    use super::{dedent, find_region, include_code};
+
+    const PAGE: &str = "tests/full/includes/docs/index.md";
+
+    fn included_code(info: &str) -> Result<String, JellyError> {
+        let mut tree = ast(&formatdoc! {"
+            ```{info}
+            ```
+        "});
+        include_code(&mut tree, Path::new(PAGE))?;
+        Ok(render(&tree))
+    }
+
+    #[test]
+    fn regions() {
+        let file = indoc! {"
+            fn main() {
+                // ANCHOR: setup
+                let x = 1;
+                // ANCHOR: inner
+                let y = 2;
+                // ANCHOR_END: inner
+                // ANCHOR_END: setup
+            }
+        "};
+
+        assert_eq!(
+            find_region(file, "setup"),
+            Some(vec![
+                "    let x = 1;",
+                "    // ANCHOR: inner",
+                "    let y = 2;",
+                "    // ANCHOR_END: inner",
+            ])
+        );
+        assert_eq!(find_region(file, "inner"), Some(vec!["    let y = 2;"]));
+        assert_eq!(find_region(file, "set"), None);
+        assert_eq!(
+            dedent(&["    a", "", "      b"]),
+            String::from("a\n\n  b\n")
+        );
+    }
+
+    #[test]
+    fn include_files() {
+        let html = included_code("include=\"../examples/main.rs\" region=\"setup\"").unwrap();
+        assert!(html.starts_with("<pre class=\"language-rs\">"), "{html}");
+        assert!(html.contains("config"));
+        assert!(!html.contains("ANCHOR"));
+        assert!(!html.contains("fn main"));
+
+        let html = included_code("python include=\"../examples/main.rs\" lines=\"1,3\"").unwrap();
+        assert!(
+            html.starts_with("<pre class=\"language-python\">"),
+            "{html}"
+        );
+        assert_eq!(html.matches("<span class=\"line\">").count(), 2);
+
+        let mut tree = ast("```rust include=\"../examples/main.rs\"\n```\n");
+        assert_eq!(
+            include_code(&mut tree, Path::new(PAGE)).unwrap(),
+            vec![PathBuf::from(
+                "tests/full/includes/docs/../examples/main.rs"
+            )]
+        );
+    }
+
+    #[test]
+    fn annotated_includes() {
+        let mut tree = ast(indoc! {r#"
+            ```python
+            x = 1  # (1)
+            ```
+
+            1. Not included
+
+            ```rust include="../examples/annotated.rs"
+            ```
+
+            1. The first value
+            2. The second value
+        "#});
+        include_code(&mut tree, Path::new(PAGE)).unwrap();
+        let html = render(&tree);
+
+        assert!(!html.contains("// ("), "{html}");
+        assert!(html.contains(
+            "<a class=\"code-annotation\" href=\"#annotation-2-2\" aria-label=\"Annotation 2\">2</a></span>\n"
+        ));
+        assert!(html.contains(indoc! {r#"
+            <ol class="code-annotations">
+            <li id="annotation-2-1">The first value</li>
+            <li id="annotation-2-2">The second value</li>
+        "#}));
+    }
+
+    #[test]
+    fn missing_includes() {
+        match included_code("include=nope.rs") {
+            Err(JellyError::IncludeNotFound(page, path)) => {
+                assert_eq!(page, PathBuf::from(PAGE));
+                assert_eq!(path, PathBuf::from("tests/full/includes/docs/nope.rs"));
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+
+        match included_code("include=../examples/main.rs region=nope") {
+            Err(JellyError::MissingRegion(_, region, _)) => assert_eq!(region, "nope"),
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+}
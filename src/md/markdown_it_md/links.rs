@@ -0,0 +1,158 @@
+use markdown_it::{plugins::cmark::inline::link::Link, Node};
+use serde::Serialize;
+
+use crate::utils::is_relative_url;
+
+use super::image::FancyImage;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+pub(crate) enum ReferenceKind {
+    Link,
+    Image,
+}
+
+// A link or image in a page, used for checking that the things pages point to actually exist
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub(crate) struct Reference {
+    pub(crate) kind: ReferenceKind,
+    pub(crate) url: String,
+    pub(crate) line: Option<usize>,
+}
+
+// A relative link that couldn't be resolved. Lines start at 1 and are relative to the Markdown
+// source that was parsed.
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) struct UnresolvedLink {
+    pub(crate) url: String,
+    pub(crate) line: Option<usize>,
+}
+
+// Passes every relative link URL through the resolver, which either returns the URL to use
+// instead or None if the link points at something that doesn't exist.
+pub(crate) fn resolve_links<F>(root: &mut Node, source: &str, resolve: F) -> Vec<UnresolvedLink>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut unresolved: Vec<UnresolvedLink> = Vec::new();
+
+    root.walk_mut(|node, _| {
+        let srcmap = node.srcmap;
+
+        if let Some(link) = node.cast_mut::<Link>() {
+            if !is_relative_url(&link.url) {
+                return;
+            }
+
+            match resolve(&link.url) {
+                Some(url) => link.url = url,
+                None => unresolved.push(UnresolvedLink {
+                    url: link.url.clone(),
+                    line: srcmap.map(|pos| line_at(source, pos.get_byte_offsets().0)),
+                }),
+            }
+        }
+    });
+
+    unresolved
+}
+
+pub(crate) fn collect_references(root: &Node, source: &str) -> Vec<Reference> {
+    let mut references: Vec<Reference> = Vec::new();
+
+    root.walk(|node, _| {
+        let reference = if let Some(link) = node.cast::<Link>() {
+            Some((ReferenceKind::Link, &link.url))
+        } else {
+            node.cast::<FancyImage>()
+                .map(|image| (ReferenceKind::Image, &image.url))
+        };
+
+        if let Some((kind, url)) = reference {
+            references.push(Reference {
+                kind,
+                url: url.clone(),
+                line: node
+                    .srcmap
+                    .map(|pos| line_at(source, pos.get_byte_offsets().0)),
+            });
+        }
+    });
+
+    references
+}
+
+pub(crate) fn line_at(source: &str, offset: usize) -> usize {
+    source[..offset.min(source.len())].matches('\n').count() + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::md::{ast, render};
+
+    use super::{collect_references, resolve_links, Reference, ReferenceKind, UnresolvedLink};
+
+    #[test]
+    fn resolve_relative_links() {
+        let md = indoc! {"
+            # Title
+
+            See [setup](../setup/index.md#install) and [the site](https://example.com).
+
+            A [missing page](missing.md) and an [anchor](#title).
+        "};
+
+        let mut tree = ast(md);
+        let unresolved = resolve_links(&mut tree, md, |url| match url {
+            "../setup/index.md#install" => Some(String::from("/setup#install")),
+            _ => None,
+        });
+
+        assert_eq!(
+            unresolved,
+            vec![UnresolvedLink {
+                url: String::from("missing.md"),
+                line: Some(5),
+            }]
+        );
+
+        let html = render(&tree);
+        assert!(html.contains("<a href=\"/setup#install\">setup</a>"));
+        assert!(html.contains("<a href=\"https://example.com\">the site</a>"));
+        assert!(html.contains("<a href=\"missing.md\">missing page</a>"));
+        assert!(html.contains("<a href=\"#title\">anchor</a>"));
+    }
+
+    #[test]
+    fn references() {
+        let md = indoc! {"
+            A [link](/foo#bar).
+
+            ![image](img.png)
+
+            Some [more](#baz) [links](https://example.com).
+        "};
+
+        let references = collect_references(&ast(md), md);
+
+        let expected = vec![
+            (ReferenceKind::Link, "/foo#bar", 1),
+            (ReferenceKind::Image, "img.png", 3),
+            (ReferenceKind::Link, "#baz", 5),
+            (ReferenceKind::Link, "https://example.com", 5),
+        ];
+
+        assert_eq!(
+            references,
+            expected
+                .into_iter()
+                .map(|(kind, url, line)| Reference {
+                    kind,
+                    url: String::from(url),
+                    line: Some(line),
+                })
+                .collect::<Vec<Reference>>()
+        );
+    }
+}
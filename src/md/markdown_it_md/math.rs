@@ -0,0 +1,334 @@
+use markdown_it::{
+    parser::{
+        block::{BlockRule, BlockState},
+        inline::{InlineRule, InlineState},
+    },
+    plugins::cmark::block::paragraph::ParagraphScanner,
+    MarkdownIt, Node, NodeValue, Renderer,
+};
+
+use super::{links::line_at, mathml::tex_to_mathml};
+
+const INLINE_DELIMITER: &str = "$";
+const DISPLAY_DELIMITER: &str = "$$";
+
+// TeX math, rendered to MathML at build time so that pages don't need any JavaScript for it
+#[derive(Debug)]
+pub(super) struct Math {
+    pub(super) tex: String,
+    display: bool,
+    // On lines of its own rather than within a paragraph
+    block: bool,
+    mathml: String,
+    // What's wrong with the TeX, for the build warnings
+    errors: Vec<String>,
+}
+
+impl Math {
+    fn new(tex: &str, display: bool, block: bool) -> Self {
+        let tex = tex.trim();
+        let (mathml, errors) = tex_to_mathml(tex, display);
+
+        Self {
+            tex: String::from(tex),
+            display,
+            block,
+            mathml,
+            errors,
+        }
+    }
+
+    // What gets indexed for search: the TeX without its markup, so that `$\alpha_i$` can be
+    // found as "alpha i"
+    pub(super) fn search_text(&self) -> String {
+        self.tex
+            .replace(['\\', '{', '}', '^', '_', '&', '$'], " ")
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ")
+    }
+}
+
+impl NodeValue for Math {
+    fn render(&self, _: &Node, fmt: &mut dyn Renderer) {
+        let mut attrs = Vec::new();
+        if self.display {
+            attrs.push(("display", String::from("block")));
+        }
+        attrs.push(("data-tex", self.tex.clone()));
+
+        if self.block {
+            fmt.cr();
+        }
+        fmt.open("math", &attrs);
+        fmt.text_raw(&self.mathml);
+        fmt.close("math");
+        if self.block {
+            fmt.cr();
+        }
+    }
+}
+
+// A problem with the TeX of some math, with its line in the Markdown source
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) struct MathError {
+    pub(crate) tex: String,
+    pub(crate) message: String,
+    pub(crate) line: Option<usize>,
+}
+
+pub(crate) fn math_errors(root: &Node, source: &str) -> Vec<MathError> {
+    let mut errors: Vec<MathError> = Vec::new();
+
+    root.walk(|node, _| {
+        let Some(math) = node.cast::<Math>() else {
+            return;
+        };
+
+        for message in &math.errors {
+            errors.push(MathError {
+                tex: math.tex.clone(),
+                message: message.clone(),
+                line: node
+                    .srcmap
+                    .map(|pos| line_at(source, pos.get_byte_offsets().0)),
+            });
+        }
+    });
+
+    errors
+}
+
+// Where the closing delimiter is, skipping escaped characters like `\$`
+fn find_closing(s: &str, delimiter: &str) -> Option<usize> {
+    let mut escaped = false;
+
+    for (idx, c) in s.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if s[idx..].starts_with(delimiter) {
+            return Some(idx);
+        }
+    }
+
+    None
+}
+
+// `$...$` for inline math and `$$...$$` for display math within a paragraph. Like Pandoc, the
+// opening `$` can't be followed by whitespace and the closing one can't be preceded by whitespace
+// or followed by a digit, so that prices like $5 and $10 stay text.
+struct InlineMathScanner;
+
+impl InlineRule for InlineMathScanner {
+    const MARKER: char = '$';
+
+    fn run(state: &mut InlineState) -> Option<(Node, usize)> {
+        let input = &state.src[state.pos..state.pos_max];
+
+        let (delimiter, display) = if input.starts_with(DISPLAY_DELIMITER) {
+            (DISPLAY_DELIMITER, true)
+        } else {
+            (INLINE_DELIMITER, false)
+        };
+
+        let rest = &input[delimiter.len()..];
+        let end = find_closing(rest, delimiter)?;
+        let tex = &rest[..end];
+
+        if tex.trim().is_empty() {
+            return None;
+        }
+
+        if !display {
+            let after = &rest[end + delimiter.len()..];
+
+            if tex.starts_with(char::is_whitespace)
+                || tex.ends_with(char::is_whitespace)
+                || after.starts_with(|c: char| c.is_ascii_digit())
+            {
+                return None;
+            }
+        }
+
+        Some((
+            Node::new(Math::new(tex, display, false)),
+            end + 2 * delimiter.len(),
+        ))
+    }
+}
+
+// Display math on lines of its own: `$$` on the first line and `$$` at the end of the last one,
+// which can be the same line. TeX doesn't allow blank lines in math, so a `$$` without a closing
+// one before the next blank line is left as text.
+struct MathBlockScanner;
+
+impl MathBlockScanner {
+    // The last line of the block and the TeX in it
+    fn find_end(state: &BlockState) -> Option<(usize, String)> {
+        if state.line_indent(state.line) >= state.md.max_indent {
+            return None;
+        }
+
+        let first = state
+            .get_line(state.line)
+            .trim_end()
+            .strip_prefix(DISPLAY_DELIMITER)?;
+
+        if let Some(tex) = first.strip_suffix(DISPLAY_DELIMITER) {
+            return (!tex.trim().is_empty()).then(|| (state.line, String::from(tex)));
+        }
+
+        // Math followed by text is left to the inline rule
+        if first.contains(DISPLAY_DELIMITER) {
+            return None;
+        }
+
+        let mut tex = String::from(first);
+
+        for line in state.line + 1..state.line_max {
+            // Outdented lines end the enclosing list item
+            if state.is_empty(line) || state.line_indent(line) < 0 {
+                return None;
+            }
+
+            let text = state.get_line(line).trim_end();
+            tex.push('\n');
+
+            if let Some(last) = text.strip_suffix(DISPLAY_DELIMITER) {
+                tex.push_str(last);
+                return (!last.contains(DISPLAY_DELIMITER) && !tex.trim().is_empty())
+                    .then_some((line, tex));
+            }
+
+            if text.contains(DISPLAY_DELIMITER) {
+                return None;
+            }
+
+            tex.push_str(text);
+        }
+
+        None
+    }
+}
+
+impl BlockRule for MathBlockScanner {
+    fn check(state: &mut BlockState) -> Option<()> {
+        Self::find_end(state).map(|_| ())
+    }
+
+    fn run(state: &mut BlockState) -> Option<(Node, usize)> {
+        let (end_line, tex) = Self::find_end(state)?;
+
+        Some((
+            Node::new(Math::new(&tex, true, true)),
+            end_line + 1 - state.line,
+        ))
+    }
+}
+
+pub(super) fn add_math_rules(md: &mut MarkdownIt) {
+    md.inline.add_rule::<InlineMathScanner>();
+    md.block
+        .add_rule::<MathBlockScanner>()
+        .before::<ParagraphScanner>();
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::{
+        md::{ast, build_search_index_for_page},
+        tests::test_markdown_produces_expected_html,
+    };
+
+    use super::{math_errors, MathError};
+
+    #[test]
+    fn math() {
+        let cases: Vec<(&str, &str)> = vec![
+            (
+                "Euler: $e^{i\\pi} = -1$.",
+                "<p>Euler: <math data-tex=\"e^{i\\pi} = -1\"><mrow><msup><mi>e</mi><mrow><mi>i</mi><mi>π</mi></mrow></msup><mo>=</mo><mo>−</mo><mn>1</mn></mrow></math>.</p>\n",
+            ),
+            (
+                indoc! {"
+                    $$
+                    a < b
+                    $$
+                "},
+                "<math display=\"block\" data-tex=\"a &lt; b\"><mrow><mi>a</mi><mo>&lt;</mo><mi>b</mi></mrow></math>\n",
+            ),
+            (
+                "$$x^2$$\n",
+                "<math display=\"block\" data-tex=\"x^2\"><msup><mi>x</mi><mn>2</mn></msup></math>\n",
+            ),
+            (
+                "It costs $5 or $10.\n\nEscaped: \\$x$.",
+                "<p>It costs $5 or $10.</p>\n<p>Escaped: $x$.</p>\n",
+            ),
+            ("$ x $ and $$", "<p>$ x $ and $$</p>\n"),
+            (
+                "$$\nx\n\nA paragraph.\n\n$$\n",
+                "<p>$$\nx</p>\n<p>A paragraph.</p>\n<p>$$</p>\n",
+            ),
+            (
+                "$$x$$ inline",
+                "<p><math display=\"block\" data-tex=\"x\"><mi>x</mi></math> inline</p>\n",
+            ),
+        ];
+
+        test_markdown_produces_expected_html(cases);
+    }
+
+    #[test]
+    fn math_in_search() {
+        let tree = ast(indoc! {r#"
+            The sum $\sum_{i=1}^n x_i$ is
+
+            $$
+            \frac{n(n+1)}{2}
+            $$
+        "#});
+
+        let index = build_search_index_for_page("Sums", "/sums", &[], &tree);
+        let index = format!("{index:?}");
+
+        assert!(
+            index.contains("The sum sum i=1 n x i is frac n(n+1) 2"),
+            "{index}"
+        );
+        assert!(!index.contains("<m"));
+    }
+
+    #[test]
+    fn malformed_math() {
+        let md = indoc! {r#"
+            Fine: $x^2$.
+
+            Broken: $\frac{$ and $\foo$.
+        "#};
+        let tree = ast(md);
+
+        assert!(tree
+            .render()
+            .contains("<math data-tex=\"\\frac{\"><merror><mtext>\\frac{</mtext></merror></math>"));
+        assert_eq!(
+            math_errors(&tree, md),
+            vec![
+                MathError {
+                    tex: String::from("\\frac{"),
+                    message: String::from("missing closing brace"),
+                    line: Some(3),
+                },
+                MathError {
+                    tex: String::from("\\foo"),
+                    message: String::from("unknown command \\foo"),
+                    line: Some(3),
+                },
+            ]
+        );
+    }
+}
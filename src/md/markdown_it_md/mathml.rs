@@ -0,0 +1,1292 @@
+use markdown_it::common::utils::escape_html;
+
+// A TeX to MathML converter for the subset of LaTeX math that shows up in documentation: scripts,
+// fractions, roots, Greek letters, operators and symbols, font styles, accents, delimiters and
+// matrix-like environments. Problems are returned along with the MathML rather than failing the
+// build: commands it doesn't know are shown as errors in place, and formulas with unbalanced
+// braces, delimiters or environments are shown as an error as a whole.
+pub(super) fn tex_to_mathml(tex: &str, display: bool) -> (String, Vec<String>) {
+    let mut parser = Parser {
+        chars: tex.chars().collect(),
+        pos: 0,
+        display,
+        variant: None,
+        end_name: String::new(),
+        errors: Vec::new(),
+        unbalanced: false,
+    };
+    let mut items: Vec<Item> = Vec::new();
+
+    loop {
+        let (row, stop) = parser.parse_row(false);
+        items.extend(row);
+
+        if stop == Stop::End {
+            break;
+        }
+        parser.unexpected(stop, Stop::End);
+    }
+
+    let mathml = if parser.unbalanced {
+        format!("<merror><mtext>{}</mtext></merror>", escape_html(tex))
+    } else {
+        mrow(items)
+    };
+
+    (mathml, parser.errors)
+}
+
+const GREEK: [(&str, char); 40] = [
+    ("alpha", 'α'),
+    ("beta", 'β'),
+    ("gamma", 'γ'),
+    ("delta", 'δ'),
+    ("epsilon", 'ϵ'),
+    ("varepsilon", 'ε'),
+    ("zeta", 'ζ'),
+    ("eta", 'η'),
+    ("theta", 'θ'),
+    ("vartheta", 'ϑ'),
+    ("iota", 'ι'),
+    ("kappa", 'κ'),
+    ("lambda", 'λ'),
+    ("mu", 'μ'),
+    ("nu", 'ν'),
+    ("xi", 'ξ'),
+    ("omicron", 'ο'),
+    ("pi", 'π'),
+    ("varpi", 'ϖ'),
+    ("rho", 'ρ'),
+    ("varrho", 'ϱ'),
+    ("sigma", 'σ'),
+    ("varsigma", 'ς'),
+    ("tau", 'τ'),
+    ("upsilon", 'υ'),
+    ("phi", 'ϕ'),
+    ("varphi", 'φ'),
+    ("chi", 'χ'),
+    ("psi", 'ψ'),
+    ("omega", 'ω'),
+    ("Gamma", 'Γ'),
+    ("Delta", 'Δ'),
+    ("Theta", 'Θ'),
+    ("Lambda", 'Λ'),
+    ("Xi", 'Ξ'),
+    ("Pi", 'Π'),
+    ("Sigma", 'Σ'),
+    ("Phi", 'Φ'),
+    ("Psi", 'Ψ'),
+    ("Omega", 'Ω'),
+];
+
+// Symbols that are identifiers rather than operators
+const IDENTIFIERS: [(&str, &str); 15] = [
+    ("infty", "∞"),
+    ("partial", "∂"),
+    ("nabla", "∇"),
+    ("emptyset", "∅"),
+    ("varnothing", "∅"),
+    ("ell", "ℓ"),
+    ("hbar", "ℏ"),
+    ("imath", "ı"),
+    ("jmath", "ȷ"),
+    ("Re", "ℜ"),
+    ("Im", "ℑ"),
+    ("aleph", "ℵ"),
+    ("wp", "℘"),
+    ("top", "⊤"),
+    ("angle", "∠"),
+];
+
+const OPERATORS: [(&str, &str); 107] = [
+    ("times", "×"),
+    ("cdot", "⋅"),
+    ("pm", "±"),
+    ("mp", "∓"),
+    ("div", "÷"),
+    ("ast", "∗"),
+    ("star", "⋆"),
+    ("circ", "∘"),
+    ("bullet", "∙"),
+    ("oplus", "⊕"),
+    ("ominus", "⊖"),
+    ("otimes", "⊗"),
+    ("odot", "⊙"),
+    ("cup", "∪"),
+    ("cap", "∩"),
+    ("setminus", "∖"),
+    ("backslash", "\\"),
+    ("wedge", "∧"),
+    ("land", "∧"),
+    ("vee", "∨"),
+    ("lor", "∨"),
+    ("neg", "¬"),
+    ("lnot", "¬"),
+    ("leq", "≤"),
+    ("le", "≤"),
+    ("geq", "≥"),
+    ("ge", "≥"),
+    ("neq", "≠"),
+    ("ne", "≠"),
+    ("ll", "≪"),
+    ("gg", "≫"),
+    ("approx", "≈"),
+    ("sim", "∼"),
+    ("simeq", "≃"),
+    ("cong", "≅"),
+    ("equiv", "≡"),
+    ("propto", "∝"),
+    ("prec", "≺"),
+    ("succ", "≻"),
+    ("preceq", "⪯"),
+    ("succeq", "⪰"),
+    ("in", "∈"),
+    ("notin", "∉"),
+    ("ni", "∋"),
+    ("subset", "⊂"),
+    ("supset", "⊃"),
+    ("subseteq", "⊆"),
+    ("supseteq", "⊇"),
+    ("mid", "∣"),
+    ("parallel", "∥"),
+    ("perp", "⊥"),
+    ("bot", "⊥"),
+    ("vdash", "⊢"),
+    ("models", "⊨"),
+    ("to", "→"),
+    ("rightarrow", "→"),
+    ("leftarrow", "←"),
+    ("gets", "←"),
+    ("leftrightarrow", "↔"),
+    ("Rightarrow", "⇒"),
+    ("Leftarrow", "⇐"),
+    ("Leftrightarrow", "⇔"),
+    ("implies", "⟹"),
+    ("impliedby", "⟸"),
+    ("iff", "⟺"),
+    ("mapsto", "↦"),
+    ("longrightarrow", "⟶"),
+    ("longleftarrow", "⟵"),
+    ("longmapsto", "⟼"),
+    ("uparrow", "↑"),
+    ("downarrow", "↓"),
+    ("hookrightarrow", "↪"),
+    ("forall", "∀"),
+    ("exists", "∃"),
+    ("nexists", "∄"),
+    ("therefore", "∴"),
+    ("because", "∵"),
+    ("ldots", "…"),
+    ("dots", "…"),
+    ("cdots", "⋯"),
+    ("vdots", "⋮"),
+    ("ddots", "⋱"),
+    ("colon", ":"),
+    ("prime", "′"),
+    ("vert", "|"),
+    ("lvert", "|"),
+    ("rvert", "|"),
+    ("Vert", "‖"),
+    ("lVert", "‖"),
+    ("rVert", "‖"),
+    ("langle", "⟨"),
+    ("rangle", "⟩"),
+    ("lfloor", "⌊"),
+    ("rfloor", "⌋"),
+    ("lceil", "⌈"),
+    ("rceil", "⌉"),
+    ("lbrace", "{"),
+    ("rbrace", "}"),
+    ("{", "{"),
+    ("}", "}"),
+    ("|", "‖"),
+    ("%", "%"),
+    ("#", "#"),
+    ("&", "&"),
+    ("$", "$"),
+    ("_", "_"),
+    ("/", "/"),
+];
+
+// Large operators, and whether their limits go above and below them in display math
+const LARGE_OPERATORS: [(&str, &str, bool); 13] = [
+    ("sum", "∑", true),
+    ("prod", "∏", true),
+    ("coprod", "∐", true),
+    ("bigcup", "⋃", true),
+    ("bigcap", "⋂", true),
+    ("bigoplus", "⨁", true),
+    ("bigotimes", "⨂", true),
+    ("bigvee", "⋁", true),
+    ("bigwedge", "⋀", true),
+    ("int", "∫", false),
+    ("iint", "∬", false),
+    ("iiint", "∭", false),
+    ("oint", "∮", false),
+];
+
+// Named functions, and whether their limits go below them in display math
+const FUNCTIONS: [(&str, bool); 33] = [
+    ("sin", false),
+    ("cos", false),
+    ("tan", false),
+    ("cot", false),
+    ("sec", false),
+    ("csc", false),
+    ("sinh", false),
+    ("cosh", false),
+    ("tanh", false),
+    ("coth", false),
+    ("arcsin", false),
+    ("arccos", false),
+    ("arctan", false),
+    ("log", false),
+    ("ln", false),
+    ("lg", false),
+    ("exp", false),
+    ("deg", false),
+    ("dim", false),
+    ("ker", false),
+    ("hom", false),
+    ("arg", false),
+    ("lim", true),
+    ("liminf", true),
+    ("limsup", true),
+    ("max", true),
+    ("min", true),
+    ("sup", true),
+    ("inf", true),
+    ("det", true),
+    ("gcd", true),
+    ("Pr", true),
+    ("argmax", true),
+];
+
+// Accents, with the character, whether it goes over the base and whether it stretches
+const ACCENTS: [(&str, &str, bool, bool); 19] = [
+    ("hat", "^", true, false),
+    ("widehat", "^", true, true),
+    ("check", "ˇ", true, false),
+    ("tilde", "˜", true, false),
+    ("widetilde", "˜", true, true),
+    ("bar", "¯", true, false),
+    ("overline", "‾", true, true),
+    ("vec", "→", true, false),
+    ("overrightarrow", "→", true, true),
+    ("overleftarrow", "←", true, true),
+    ("dot", "˙", true, false),
+    ("ddot", "¨", true, false),
+    ("acute", "´", true, false),
+    ("grave", "`", true, false),
+    ("breve", "˘", true, false),
+    ("underline", "_", false, true),
+    ("overbrace", "⏞", true, true),
+    ("underbrace", "⏟", false, true),
+    ("underrightarrow", "→", false, true),
+];
+
+const SPACES: [(&str, &str); 10] = [
+    (",", "0.1667em"),
+    ("thinspace", "0.1667em"),
+    (":", "0.2222em"),
+    (">", "0.2222em"),
+    ("medspace", "0.2222em"),
+    (";", "0.2778em"),
+    ("thickspace", "0.2778em"),
+    (" ", "0.25em"),
+    ("quad", "1em"),
+    ("qquad", "2em"),
+];
+
+// Sizes of `\big` and friends
+const DELIMITER_SIZES: [(&str, &str); 4] = [
+    ("big", "1.2em"),
+    ("Big", "1.8em"),
+    ("bigg", "2.4em"),
+    ("Bigg", "3em"),
+];
+
+// Environments and the delimiters around them
+const ENVIRONMENTS: [(&str, &str, &str); 13] = [
+    ("matrix", "", ""),
+    ("smallmatrix", "", ""),
+    ("pmatrix", "(", ")"),
+    ("bmatrix", "[", "]"),
+    ("Bmatrix", "{", "}"),
+    ("vmatrix", "|", "|"),
+    ("Vmatrix", "‖", "‖"),
+    ("cases", "{", ""),
+    ("array", "", ""),
+    ("aligned", "", ""),
+    ("align", "", ""),
+    ("align*", "", ""),
+    ("gathered", "", ""),
+];
+
+fn lookup<T: Copy>(table: &[(&str, T)], name: &str) -> Option<T> {
+    table.iter().find(|(n, _)| *n == name).map(|(_, v)| *v)
+}
+
+// Font styles. MathML Core only supports `normal`, so the others are mapped to the letters and
+// digits in the Mathematical Alphanumeric Symbols block.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Variant {
+    Normal,
+    Bold,
+    Italic,
+    BoldItalic,
+    Script,
+    Fraktur,
+    DoubleStruck,
+    SansSerif,
+    Monospace,
+}
+
+impl Variant {
+    fn parse(command: &str) -> Option<Self> {
+        match command {
+            "mathrm" | "rm" => Some(Self::Normal),
+            "mathbf" | "bf" => Some(Self::Bold),
+            "boldsymbol" | "bm" => Some(Self::BoldItalic),
+            "mathit" => Some(Self::Italic),
+            "mathcal" | "mathscr" => Some(Self::Script),
+            "mathfrak" => Some(Self::Fraktur),
+            "mathbb" => Some(Self::DoubleStruck),
+            "mathsf" => Some(Self::SansSerif),
+            "mathtt" => Some(Self::Monospace),
+            _ => None,
+        }
+    }
+
+    fn apply(self, c: char) -> char {
+        // Letters that were in Unicode before the block and are left out of it
+        let exception = match (self, c) {
+            (Self::Italic, 'h') => Some('ℎ'),
+            (Self::Script, 'B') => Some('ℬ'),
+            (Self::Script, 'E') => Some('ℰ'),
+            (Self::Script, 'F') => Some('ℱ'),
+            (Self::Script, 'H') => Some('ℋ'),
+            (Self::Script, 'I') => Some('ℐ'),
+            (Self::Script, 'L') => Some('ℒ'),
+            (Self::Script, 'M') => Some('ℳ'),
+            (Self::Script, 'R') => Some('ℛ'),
+            (Self::Script, 'e') => Some('ℯ'),
+            (Self::Script, 'g') => Some('ℊ'),
+            (Self::Script, 'o') => Some('ℴ'),
+            (Self::Fraktur, 'C') => Some('ℭ'),
+            (Self::Fraktur, 'H') => Some('ℌ'),
+            (Self::Fraktur, 'I') => Some('ℑ'),
+            (Self::Fraktur, 'R') => Some('ℜ'),
+            (Self::Fraktur, 'Z') => Some('ℨ'),
+            (Self::DoubleStruck, 'C') => Some('ℂ'),
+            (Self::DoubleStruck, 'H') => Some('ℍ'),
+            (Self::DoubleStruck, 'N') => Some('ℕ'),
+            (Self::DoubleStruck, 'P') => Some('ℙ'),
+            (Self::DoubleStruck, 'Q') => Some('ℚ'),
+            (Self::DoubleStruck, 'R') => Some('ℝ'),
+            (Self::DoubleStruck, 'Z') => Some('ℤ'),
+            _ => None,
+        };
+
+        if let Some(c) = exception {
+            return c;
+        }
+
+        // Where the capital and small Greek letters start, for the styles that have them. Both
+        // run in the same order as the Greek and Coptic block, so the offsets carry over.
+        let greek = match self {
+            Self::Bold => Some((0x1D6A8, 0x1D6C2)),
+            Self::Italic => Some((0x1D6E2, 0x1D6FC)),
+            Self::BoldItalic => Some((0x1D71C, 0x1D736)),
+            _ => None,
+        };
+
+        match (c, greek) {
+            ('Α'..='Ω', Some((upper, _))) => {
+                return char::from_u32(upper + (c as u32 - 'Α' as u32)).unwrap_or(c)
+            }
+            ('α'..='ω', Some((_, lower))) => {
+                return char::from_u32(lower + (c as u32 - 'α' as u32)).unwrap_or(c)
+            }
+            _ => {}
+        }
+
+        // Where the capital letters, small letters and digits start
+        let (upper, lower, digits) = match self {
+            Self::Normal => return c,
+            Self::Bold => (0x1D400, 0x1D41A, Some(0x1D7CE)),
+            Self::Italic => (0x1D434, 0x1D44E, None),
+            Self::BoldItalic => (0x1D468, 0x1D482, Some(0x1D7CE)),
+            Self::Script => (0x1D49C, 0x1D4B6, None),
+            Self::Fraktur => (0x1D504, 0x1D51E, None),
+            Self::DoubleStruck => (0x1D538, 0x1D552, Some(0x1D7D8)),
+            Self::SansSerif => (0x1D5A0, 0x1D5BA, Some(0x1D7E2)),
+            Self::Monospace => (0x1D670, 0x1D68A, Some(0x1D7F6)),
+        };
+
+        let code = match c {
+            'A'..='Z' => upper + (c as u32 - 'A' as u32),
+            'a'..='z' => lower + (c as u32 - 'a' as u32),
+            '0'..='9' => match digits {
+                Some(digits) => digits + (c as u32 - '0' as u32),
+                None => return c,
+            },
+            _ => return c,
+        };
+
+        char::from_u32(code).unwrap_or(c)
+    }
+}
+
+// A piece of MathML, and whether scripts attached to it go below and above it in display math
+#[derive(Debug)]
+struct Item {
+    mathml: String,
+    limits: bool,
+}
+
+impl Item {
+    fn new(mathml: String) -> Self {
+        Self {
+            mathml,
+            limits: false,
+        }
+    }
+
+    fn mo(text: &str) -> Self {
+        Self::new(format!("<mo>{}</mo>", escape_html(text)))
+    }
+}
+
+fn styled(variant: Option<Variant>, c: char) -> Item {
+    match variant {
+        Some(Variant::Normal) => Item::new(format!(
+            "<mi mathvariant=\"normal\">{}</mi>",
+            escape_html(&c.to_string())
+        )),
+        Some(variant) => Item::new(format!("<mi>{}</mi>", variant.apply(c))),
+        None => Item::new(format!("<mi>{}</mi>", escape_html(&c.to_string()))),
+    }
+}
+
+fn mrow(items: Vec<Item>) -> String {
+    if items.len() == 1 {
+        return items
+            .into_iter()
+            .next()
+            .map(|i| i.mathml)
+            .unwrap_or_default();
+    }
+
+    let mut mathml = String::from("<mrow>");
+    for item in items {
+        mathml.push_str(&item.mathml);
+    }
+    mathml.push_str("</mrow>");
+    mathml
+}
+
+fn fence(delimiter: &str, size: Option<&str>) -> String {
+    if delimiter.is_empty() {
+        return String::new();
+    }
+
+    let delimiter = escape_html(delimiter);
+
+    match size {
+        Some(size) => {
+            format!("<mo stretchy=\"true\" minsize=\"{size}\" maxsize=\"{size}\">{delimiter}</mo>")
+        }
+        None => format!("<mo fence=\"true\" stretchy=\"true\">{delimiter}</mo>"),
+    }
+}
+
+// Why a row ended
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Stop {
+    End,
+    Group,
+    Right,
+    Environment,
+    Cell,
+    Row,
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    display: bool,
+    // Set while parsing the argument of a font command like `\mathbf`
+    variant: Option<Variant>,
+    // The name of the environment that the last `\end` closed
+    end_name: String,
+    errors: Vec<String>,
+    unbalanced: bool,
+}
+
+impl Parser {
+    fn error(&mut self, message: String) {
+        self.errors.push(message);
+    }
+
+    // Only the first of these is reported since whatever follows it is usually off as well
+    fn unbalanced(&mut self, message: String) {
+        if !self.unbalanced {
+            self.error(message);
+        }
+        self.unbalanced = true;
+    }
+
+    // Records that a row ended with something other than what closes it, e.g. a `}` that no `{`
+    // opened or a `\left` without a `\right`
+    fn unexpected(&mut self, stop: Stop, expected: Stop) {
+        let message = match (stop, expected) {
+            (Stop::End, Stop::Group) => "missing closing brace",
+            (Stop::End, Stop::Right) => "\\left without a matching \\right",
+            (Stop::End, Stop::Environment) => "\\begin without a matching \\end",
+            (Stop::Group, _) => "closing brace without a matching opening one",
+            (Stop::Right, _) => "\\right without a matching \\left",
+            (Stop::Environment, _) => "\\end without a matching \\begin",
+            _ => return,
+        };
+
+        self.unbalanced(String::from(message));
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    // The name of the command at the current position, without consuming it: a run of letters or
+    // a single other character
+    fn peek_command(&self) -> Option<String> {
+        if self.peek() != Some('\\') {
+            return None;
+        }
+
+        let rest = &self.chars[self.pos + 1..];
+        let letters: String = rest
+            .iter()
+            .take_while(|c| c.is_ascii_alphabetic())
+            .collect();
+
+        if letters.is_empty() {
+            rest.first().map(|c| c.to_string())
+        } else {
+            Some(letters)
+        }
+    }
+
+    fn read_command(&mut self) -> Option<String> {
+        let name = self.peek_command()?;
+        self.pos += 1 + name.chars().count();
+        Some(name)
+    }
+
+    // The contents of a `{...}` group as text, for `\text` and environment names
+    fn read_raw_group(&mut self) -> String {
+        self.skip_whitespace();
+
+        if self.peek() != Some('{') {
+            return self.next().map(String::from).unwrap_or_default();
+        }
+
+        self.pos += 1;
+        let mut depth = 1;
+        let mut text = String::new();
+
+        while let Some(c) = self.next() {
+            match c {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return text;
+                    }
+                }
+                _ => {}
+            }
+            text.push(c);
+        }
+
+        self.unexpected(Stop::End, Stop::Group);
+        text
+    }
+
+    // `[...]`, like the index of `\sqrt[3]{x}`
+    fn read_optional(&mut self) -> Option<String> {
+        self.skip_whitespace();
+
+        if self.peek() != Some('[') {
+            return None;
+        }
+
+        self.pos += 1;
+        let mut text = String::new();
+        while let Some(c) = self.next() {
+            if c == ']' {
+                return Some(text);
+            }
+            text.push(c);
+        }
+
+        self.unbalanced(String::from("missing closing bracket"));
+        Some(text)
+    }
+
+    fn parse_row(&mut self, in_table: bool) -> (Vec<Item>, Stop) {
+        let mut items: Vec<Item> = Vec::new();
+
+        loop {
+            self.skip_whitespace();
+
+            let Some(c) = self.peek() else {
+                return (items, Stop::End);
+            };
+
+            match c {
+                '}' => {
+                    self.pos += 1;
+                    return (items, Stop::Group);
+                }
+                '&' => {
+                    self.pos += 1;
+                    if in_table {
+                        return (items, Stop::Cell);
+                    }
+                }
+                '^' | '_' => {
+                    self.pos += 1;
+                    let base = items.pop();
+                    items.push(self.parse_scripts(base, c));
+                }
+                '\'' => {
+                    let mut primes = String::new();
+                    while self.peek() == Some('\'') {
+                        self.pos += 1;
+                        primes.push('′');
+                    }
+                    let base = items.pop();
+                    items.push(self.attach(base, None, Some(Item::mo(&primes).mathml)));
+                }
+                '\\' => match self.peek_command().as_deref() {
+                    Some("\\") => {
+                        self.read_command();
+                        if in_table {
+                            return (items, Stop::Row);
+                        }
+                    }
+                    Some("right") => {
+                        self.read_command();
+                        return (items, Stop::Right);
+                    }
+                    Some("end") => {
+                        self.read_command();
+                        self.end_name = self.read_raw_group();
+                        return (items, Stop::Environment);
+                    }
+                    Some(limits @ ("limits" | "nolimits")) => {
+                        let limits = limits == "limits";
+                        self.read_command();
+                        if let Some(last) = items.last_mut() {
+                            last.limits = limits;
+                        }
+                    }
+                    _ => items.extend(self.parse_atom(false)),
+                },
+                _ => items.extend(self.parse_atom(false)),
+            }
+        }
+    }
+
+    fn parse_group(&mut self) -> Item {
+        // `\displaystyle` and `\textstyle` only last until the end of the group
+        let display = self.display;
+        let (items, stop) = self.parse_row(false);
+        self.display = display;
+
+        if stop != Stop::Group {
+            self.unexpected(stop, Stop::Group);
+        }
+        Item::new(mrow(items))
+    }
+
+    // A single character or a group, like the arguments of commands and scripts
+    fn parse_argument(&mut self) -> String {
+        self.skip_whitespace();
+
+        if matches!(self.peek(), None | Some('}')) {
+            self.unbalanced(String::from("missing argument"));
+            return String::from("<mrow></mrow>");
+        }
+
+        self.parse_atom(true)
+            .map(|item| item.mathml)
+            .unwrap_or_else(|| String::from("<mrow></mrow>"))
+    }
+
+    fn parse_atom(&mut self, single: bool) -> Option<Item> {
+        let c = self.next()?;
+
+        let item = match c {
+            '{' => self.parse_group(),
+            '\\' => {
+                self.pos -= 1;
+                return self.parse_command();
+            }
+            '0'..='9' => {
+                let mut number = String::from(c);
+                if !single {
+                    while let Some(c) = self.peek() {
+                        let is_decimal_point = c == '.'
+                            && self
+                                .chars
+                                .get(self.pos + 1)
+                                .is_some_and(char::is_ascii_digit);
+
+                        if !c.is_ascii_digit() && !is_decimal_point {
+                            break;
+                        }
+                        number.push(c);
+                        self.pos += 1;
+                    }
+                }
+                self.number(&number)
+            }
+            c if c.is_alphabetic() => self.identifier(c),
+            '~' => Item::new(String::from("<mspace width=\"0.25em\"></mspace>")),
+            '-' => Item::mo("−"),
+            '*' => Item::mo("∗"),
+            '\'' => Item::mo("′"),
+            c => Item::mo(&c.to_string()),
+        };
+
+        Some(item)
+    }
+
+    fn identifier(&self, c: char) -> Item {
+        styled(self.variant, c)
+    }
+
+    fn number(&self, number: &str) -> Item {
+        let number: String = match self.variant {
+            Some(variant) => number.chars().map(|c| variant.apply(c)).collect(),
+            None => String::from(number),
+        };
+
+        Item::new(format!("<mn>{number}</mn>"))
+    }
+
+    fn parse_scripts(&mut self, base: Option<Item>, first: char) -> Item {
+        let mut sub: Option<String> = None;
+        let mut sup: Option<String> = None;
+        let mut marker = first;
+
+        loop {
+            let script = self.parse_argument();
+            if marker == '_' {
+                sub = Some(script);
+            } else {
+                sup = Some(script);
+            }
+
+            self.skip_whitespace();
+            match self.peek() {
+                Some('_') if sub.is_none() => marker = '_',
+                Some('^') if sup.is_none() => marker = '^',
+                _ => break,
+            }
+            self.pos += 1;
+        }
+
+        self.attach(base, sub, sup)
+    }
+
+    fn attach(&self, base: Option<Item>, sub: Option<String>, sup: Option<String>) -> Item {
+        let base = base.unwrap_or_else(|| Item::new(String::from("<mrow></mrow>")));
+        let (under, over, both) = if base.limits && self.display {
+            ("munder", "mover", "munderover")
+        } else {
+            ("msub", "msup", "msubsup")
+        };
+
+        let mathml = match (sub, sup) {
+            (Some(sub), Some(sup)) => format!("<{both}>{}{sub}{sup}</{both}>", base.mathml),
+            (Some(sub), None) => format!("<{under}>{}{sub}</{under}>", base.mathml),
+            (None, Some(sup)) => format!("<{over}>{}{sup}</{over}>", base.mathml),
+            (None, None) => base.mathml,
+        };
+
+        Item::new(mathml)
+    }
+
+    // The delimiter after `\left`, `\right`, `\big` and the like. `.` is an empty delimiter.
+    fn read_delimiter(&mut self) -> String {
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some('\\') => {
+                let name = self.read_command().unwrap_or_default();
+                lookup(&OPERATORS, &name)
+                    .map(String::from)
+                    .unwrap_or_default()
+            }
+            Some('.') => {
+                self.pos += 1;
+                String::new()
+            }
+            Some('<') => {
+                self.pos += 1;
+                String::from("⟨")
+            }
+            Some('>') => {
+                self.pos += 1;
+                String::from("⟩")
+            }
+            Some(c) => {
+                self.pos += 1;
+                c.to_string()
+            }
+            None => String::new(),
+        }
+    }
+
+    fn parse_command(&mut self) -> Option<Item> {
+        let name = self.read_command()?;
+        let name = name.as_str();
+
+        if let Some(c) = lookup(&GREEK, name) {
+            // Capital Greek letters are upright in TeX, even with `\boldsymbol`
+            let variant = match self.variant {
+                Some(Variant::BoldItalic) if c.is_uppercase() => Some(Variant::Bold),
+                None if c.is_uppercase() => Some(Variant::Normal),
+                variant => variant,
+            };
+            return Some(styled(variant, c));
+        }
+
+        if let Some(symbol) = lookup(&IDENTIFIERS, name) {
+            return Some(Item::new(format!("<mi>{symbol}</mi>")));
+        }
+
+        if let Some(symbol) = lookup(&OPERATORS, name) {
+            return Some(Item::mo(symbol));
+        }
+
+        if let Some((_, symbol, limits)) = LARGE_OPERATORS.iter().find(|(n, _, _)| *n == name) {
+            return Some(Item {
+                mathml: format!("<mo largeop=\"true\">{symbol}</mo>"),
+                limits: *limits,
+            });
+        }
+
+        if let Some(limits) = lookup(&FUNCTIONS, name) {
+            let text = match name {
+                "liminf" => "lim inf",
+                "limsup" => "lim sup",
+                "argmax" => "arg max",
+                _ => name,
+            };
+            return Some(Item {
+                mathml: format!("<mi>{text}</mi>"),
+                limits,
+            });
+        }
+
+        if let Some(width) = lookup(&SPACES, name) {
+            return Some(Item::new(format!("<mspace width=\"{width}\"></mspace>")));
+        }
+
+        if let Some(variant) = Variant::parse(name) {
+            let outer = self.variant.replace(variant);
+            let argument = self.parse_argument();
+            self.variant = outer;
+            return Some(Item::new(argument));
+        }
+
+        if let Some((_, accent, over, stretchy)) = ACCENTS.iter().find(|(n, ..)| *n == name) {
+            let base = self.parse_argument();
+            let (tag, attr) = if *over {
+                ("mover", "accent")
+            } else {
+                ("munder", "accentunder")
+            };
+
+            return Some(Item {
+                mathml: format!(
+                    "<{tag} {attr}=\"true\">{base}<mo stretchy=\"{stretchy}\">{}</mo></{tag}>",
+                    escape_html(accent)
+                ),
+                // Braces take limits, like `\underbrace{a + b}_{n}`
+                limits: name.ends_with("brace"),
+            });
+        }
+
+        if let Some(size) = lookup(&DELIMITER_SIZES, name.trim_end_matches(['l', 'r', 'm'])) {
+            let delimiter = self.read_delimiter();
+            return Some(Item::new(fence(&delimiter, Some(size))));
+        }
+
+        let item = match name {
+            "frac" | "dfrac" | "tfrac" | "cfrac" => {
+                let numerator = self.parse_argument();
+                let denominator = self.parse_argument();
+                Item::new(format!("<mfrac>{numerator}{denominator}</mfrac>"))
+            }
+            "binom" | "dbinom" | "tbinom" => {
+                let top = self.parse_argument();
+                let bottom = self.parse_argument();
+                Item::new(format!(
+                    "<mrow><mo>(</mo><mfrac linethickness=\"0\">{top}{bottom}</mfrac><mo>)</mo></mrow>"
+                ))
+            }
+            "sqrt" => match self.read_optional() {
+                Some(index) => {
+                    let (index, errors) = tex_to_mathml(&index, false);
+                    self.errors.extend(errors);
+                    let radicand = self.parse_argument();
+                    Item::new(format!("<mroot>{radicand}{index}</mroot>"))
+                }
+                None => Item::new(format!("<msqrt>{}</msqrt>", self.parse_argument())),
+            },
+            "text" | "textrm" | "textnormal" | "textit" | "textbf" | "textsf" | "texttt"
+            | "mbox" => Item::new(format!(
+                "<mtext>{}</mtext>",
+                escape_html(&self.read_raw_group())
+            )),
+            "operatorname" => {
+                let limits = self.peek() == Some('*');
+                if limits {
+                    self.pos += 1;
+                }
+                Item {
+                    mathml: format!("<mi>{}</mi>", escape_html(&self.read_raw_group())),
+                    limits,
+                }
+            }
+            "left" => {
+                let open = self.read_delimiter();
+                let mut items: Vec<Item> = Vec::new();
+                let mut close = String::new();
+
+                loop {
+                    let (row, stop) = self.parse_row(false);
+                    items.extend(row);
+
+                    match stop {
+                        Stop::Right => {
+                            close = self.read_delimiter();
+                            break;
+                        }
+                        Stop::End | Stop::Group | Stop::Environment => {
+                            self.unexpected(stop, Stop::Right);
+                            break;
+                        }
+                        Stop::Cell | Stop::Row => {}
+                    }
+                }
+
+                let mut mathml = String::from("<mrow>");
+                mathml.push_str(&fence(&open, None));
+                mathml.push_str(&mrow(items));
+                mathml.push_str(&fence(&close, None));
+                mathml.push_str("</mrow>");
+                Item::new(mathml)
+            }
+            "middle" => {
+                let delimiter = self.read_delimiter();
+                Item::new(fence(&delimiter, None))
+            }
+            "begin" => {
+                let environment = self.read_raw_group();
+                self.parse_environment(&environment)
+            }
+            "not" => {
+                // Negates the relation that follows, e.g. `\not\equiv`
+                self.skip_whitespace();
+                let mut item = self.parse_atom(true)?;
+                if let Some(start) = item.mathml.strip_suffix("</mo>") {
+                    item.mathml = format!("{start}\u{338}</mo>");
+                }
+                item
+            }
+            // Binary `a \bmod b`, and `a \equiv b \pmod{n}` or `\mod{n}` after a relation
+            "bmod" => Item::new(String::from(
+                "<mo lspace=\"0.2222em\" rspace=\"0.2222em\">mod</mo>",
+            )),
+            "pmod" => Item::new(format!(
+                "<mrow><mspace width=\"1em\"></mspace><mo>(</mo><mi>mod</mi><mspace width=\"0.3333em\"></mspace>{}<mo>)</mo></mrow>",
+                self.parse_argument()
+            )),
+            "mod" => Item::new(format!(
+                "<mrow><mspace width=\"1em\"></mspace><mi>mod</mi><mspace width=\"0.3333em\"></mspace>{}</mrow>",
+                self.parse_argument()
+            )),
+            "displaystyle" => {
+                self.display = true;
+                return None;
+            }
+            "textstyle" | "scriptstyle" => {
+                self.display = false;
+                return None;
+            }
+            "nonumber" | "notag" => return None,
+            _ => {
+                self.error(format!("unknown command \\{name}"));
+                Item::new(format!(
+                    "<merror><mtext>\\{}</mtext></merror>",
+                    escape_html(name)
+                ))
+            }
+        };
+
+        Some(item)
+    }
+
+    fn parse_environment(&mut self, name: &str) -> Item {
+        // The column specification of an array doesn't matter for the markup
+        if name == "array" {
+            self.read_raw_group();
+        }
+
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        let mut row: Vec<String> = Vec::new();
+
+        loop {
+            let (items, stop) = self.parse_row(true);
+            row.push(mrow(items));
+
+            match stop {
+                Stop::Cell => {}
+                Stop::Row => rows.push(std::mem::take(&mut row)),
+                _ => {
+                    if stop != Stop::Environment {
+                        self.unexpected(stop, Stop::Environment);
+                    } else if self.end_name != name {
+                        self.unbalanced(format!(
+                            "\\begin{{{name}}} ended by \\end{{{}}}",
+                            self.end_name
+                        ));
+                    }
+
+                    rows.push(row);
+                    break;
+                }
+            }
+        }
+
+        // A `\\` at the end of the last row doesn't start another one
+        if rows.len() > 1
+            && rows
+                .last()
+                .is_some_and(|r| r.len() == 1 && r[0] == "<mrow></mrow>")
+        {
+            rows.pop();
+        }
+
+        let mut table = match name {
+            "cases" => String::from("<mtable columnalign=\"left\">"),
+            "aligned" | "align" | "align*" => {
+                String::from("<mtable columnalign=\"right left right left\">")
+            }
+            _ => String::from("<mtable>"),
+        };
+        for row in rows {
+            table.push_str("<mtr>");
+            for cell in row {
+                table.push_str("<mtd>");
+                table.push_str(&cell);
+                table.push_str("</mtd>");
+            }
+            table.push_str("</mtr>");
+        }
+        table.push_str("</mtable>");
+
+        match ENVIRONMENTS.iter().find(|(n, _, _)| *n == name) {
+            Some((_, "", "")) => Item::new(table),
+            Some((_, open, close)) => Item::new(format!(
+                "<mrow>{}{table}{}</mrow>",
+                fence(open, None),
+                fence(close, None)
+            )),
+            None => {
+                self.error(format!("unknown environment {name}"));
+                Item::new(format!(
+                    "<merror><mtext>\\begin{{{}}}</mtext></merror>",
+                    escape_html(name)
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::tex_to_mathml;
+
+    #[test]
+    fn tex_to_mathml_conversion() {
+        let cases: Vec<(&str, &str)> = vec![
+            ("x", "<mi>x</mi>"),
+            ("x + 1", "<mrow><mi>x</mi><mo>+</mo><mn>1</mn></mrow>"),
+            ("3.14", "<mn>3.14</mn>"),
+            ("a < b", "<mrow><mi>a</mi><mo>&lt;</mo><mi>b</mi></mrow>"),
+            ("x^2", "<msup><mi>x</mi><mn>2</mn></msup>"),
+            ("x^23", "<mrow><msup><mi>x</mi><mn>2</mn></msup><mn>3</mn></mrow>"),
+            (
+                "x_i^{n+1}",
+                "<msubsup><mi>x</mi><mi>i</mi><mrow><mi>n</mi><mo>+</mo><mn>1</mn></mrow></msubsup>",
+            ),
+            ("f'", "<msup><mi>f</mi><mo>′</mo></msup>"),
+            (
+                "\\frac{a}{b}",
+                "<mfrac><mi>a</mi><mi>b</mi></mfrac>",
+            ),
+            (
+                "\\sqrt[3]{x}",
+                "<mroot><mi>x</mi><mn>3</mn></mroot>",
+            ),
+            ("\\sqrt2", "<msqrt><mn>2</mn></msqrt>"),
+            (
+                "\\alpha \\leq \\Omega",
+                "<mrow><mi>α</mi><mo>≤</mo><mi mathvariant=\"normal\">Ω</mi></mrow>",
+            ),
+            (
+                "\\mathbb{R}^n",
+                "<msup><mi>ℝ</mi><mi>n</mi></msup>",
+            ),
+            ("\\mathbf{v}", "<mi>𝐯</mi>"),
+            ("\\boldsymbol{x}", "<mi>𝒙</mi>"),
+            (
+                "\\boldsymbol{\\alpha\\Gamma}",
+                "<mrow><mi>𝜶</mi><mi>𝚪</mi></mrow>",
+            ),
+            ("\\mathbf{\\omega}", "<mi>𝛚</mi>"),
+            (
+                "a \\bmod b",
+                "<mrow><mi>a</mi><mo lspace=\"0.2222em\" rspace=\"0.2222em\">mod</mo><mi>b</mi></mrow>",
+            ),
+            (
+                "a \\equiv b \\pmod{n}",
+                "<mrow><mi>a</mi><mo>≡</mo><mi>b</mi><mrow><mspace width=\"1em\"></mspace><mo>(</mo><mi>mod</mi><mspace width=\"0.3333em\"></mspace><mi>n</mi><mo>)</mo></mrow></mrow>",
+            ),
+            (
+                "\\mathrm{d}x",
+                "<mrow><mi mathvariant=\"normal\">d</mi><mi>x</mi></mrow>",
+            ),
+            (
+                "\\text{if } x",
+                "<mrow><mtext>if </mtext><mi>x</mi></mrow>",
+            ),
+            (
+                "\\sin x",
+                "<mrow><mi>sin</mi><mi>x</mi></mrow>",
+            ),
+            (
+                "\\hat{x}",
+                "<mover accent=\"true\"><mi>x</mi><mo stretchy=\"false\">^</mo></mover>",
+            ),
+            (
+                "\\left( x \\right.",
+                "<mrow><mo fence=\"true\" stretchy=\"true\">(</mo><mi>x</mi></mrow>",
+            ),
+            ("a \\not= b", "<mrow><mi>a</mi><mo>=\u{338}</mo><mi>b</mi></mrow>"),
+            (
+                "\\begin{pmatrix} a & b \\\\ c & d \\end{pmatrix}",
+                concat!(
+                    "<mrow><mo fence=\"true\" stretchy=\"true\">(</mo><mtable>",
+                    "<mtr><mtd><mi>a</mi></mtd><mtd><mi>b</mi></mtd></mtr>",
+                    "<mtr><mtd><mi>c</mi></mtd><mtd><mi>d</mi></mtd></mtr>",
+                    "</mtable><mo fence=\"true\" stretchy=\"true\">)</mo></mrow>",
+                ),
+            ),
+            (
+                "\\foo{x}",
+                "<mrow><merror><mtext>\\foo</mtext></merror><mi>x</mi></mrow>",
+            ),
+        ];
+
+        for (tex, expected) in cases {
+            assert_eq!(tex_to_mathml(tex, false).0, expected, "{tex}");
+        }
+    }
+
+    #[test]
+    fn malformed_tex() {
+        let cases: Vec<(&str, &str)> = vec![
+            ("\\frac{", "missing closing brace"),
+            ("\\frac{a}", "missing argument"),
+            ("x}", "closing brace without a matching opening one"),
+            ("\\left( x", "\\left without a matching \\right"),
+            ("x \\right)", "\\right without a matching \\left"),
+            ("\\sqrt[3{x}", "missing closing bracket"),
+            ("\\begin{matrix} a", "\\begin without a matching \\end"),
+            (
+                "\\begin{pmatrix} a \\end{bmatrix}",
+                "\\begin{pmatrix} ended by \\end{bmatrix}",
+            ),
+        ];
+
+        for (tex, error) in cases {
+            let (mathml, errors) = tex_to_mathml(tex, false);
+
+            assert_eq!(errors, vec![String::from(error)], "{tex}");
+            assert!(mathml.starts_with("<merror><mtext>"), "{tex}: {mathml}");
+        }
+
+        // Unknown commands are shown in place
+        let (mathml, errors) = tex_to_mathml("\\foo + \\begin{nope}a\\end{nope}", false);
+        assert_eq!(
+            errors,
+            vec![
+                String::from("unknown command \\foo"),
+                String::from("unknown environment nope"),
+            ]
+        );
+        assert!(mathml.starts_with("<mrow><merror><mtext>\\foo</mtext></merror>"));
+    }
+
+    #[test]
+    fn limits() {
+        let sum = "\\sum_{i=1}^n i";
+
+        assert!(tex_to_mathml(sum, true)
+            .0
+            .starts_with("<mrow><munderover><mo largeop=\"true\">∑</mo>"));
+        assert!(tex_to_mathml(sum, false)
+            .0
+            .starts_with("<mrow><msubsup><mo largeop=\"true\">∑</mo>"));
+        assert!(tex_to_mathml("\\int_0^1", true).0.starts_with("<msubsup>"));
+        assert!(tex_to_mathml("\\lim_{x\\to 0} f(x)", true)
+            .0
+            .starts_with("<mrow><munder><mi>lim</mi>"));
+        assert!(tex_to_mathml("\\lim_{x\\to 0} f(x)", false)
+            .0
+            .starts_with("<mrow><msub><mi>lim</mi>"));
+        assert!(tex_to_mathml("\\prod_{i=1}^n x_i", true)
+            .0
+            .starts_with("<mrow><munderover><mo largeop=\"true\">∏</mo>"));
+        // \displaystyle switches inline math to display limits until the end of the group
+        assert!(tex_to_mathml("\\displaystyle\\sum_{i=1}^n i", false)
+            .0
+            .starts_with("<mrow><munderover>"));
+        assert!(tex_to_mathml("{\\displaystyle x} \\sum_{i=1}^n i", false)
+            .0
+            .contains("<msubsup><mo largeop=\"true\">∑</mo>"));
+        assert!(tex_to_mathml("\\int\\limits_0^1", true)
+            .0
+            .starts_with("<munderover>"));
+    }
+}
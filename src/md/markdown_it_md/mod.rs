@@ -1,15 +1,38 @@
+mod abbreviation;
+mod admonition;
+mod anchors;
+mod annotation;
 mod code;
+mod deflist;
+mod footnote;
 mod headings;
 mod highlight;
 mod image;
+mod include;
+mod links;
+mod math;
+mod mathml;
 mod parse;
 mod render;
 mod search;
+mod tabs;
+mod tasks;
 mod title;
 mod toc;
 
-pub(crate) use parse::{ast, render};
-pub(crate) use render::render_page;
+pub(crate) use admonition::assign_callout_ids;
+pub(crate) use anchors::anchor_ids;
+pub(crate) use code::{has_diagrams, unknown_languages};
+pub(crate) use headings::duplicate_heading_ids;
+pub(crate) use highlight::{highlighting_css, Highlighter};
+pub(crate) use image::resolve_image_urls;
+pub(crate) use include::include_code;
+pub(crate) use links::{collect_references, resolve_links, Reference, ReferenceKind};
+pub(crate) use math::math_errors;
+#[cfg(test)]
+pub(crate) use parse::ast;
+pub(crate) use parse::{ast_with_config, render};
+pub(crate) use render::Renderer;
 pub(crate) use search::{build_search_index_for_page, SearchDocument, SearchIndex};
 pub(crate) use title::get_document_title;
 pub(crate) use toc::TableOfContents;
@@ -1,17 +1,83 @@
-use markdown_it::Node;
+use markdown_it::{
+    parser::inline::Text,
+    plugins::cmark::{
+        block::list::{BulletList, OrderedList},
+        inline::newline::Softbreak,
+    },
+    Node,
+};
 
-use super::{code::add_code_block_rule, headings::add_heading_rule, image::add_image_rule};
+use crate::config::MarkdownConfig;
 
-use super::headings::FancyHeading;
+use super::{
+    abbreviation::add_abbreviation_rules, admonition::add_admonition_rules,
+    code::add_code_block_rule, deflist::add_definition_list_rule, footnote::add_footnote_rules,
+    headings::add_heading_rule, highlight::Highlighter, image::add_image_rule,
+    math::add_math_rules, tabs::add_tabs_rule, tasks::add_task_list_rule,
+};
+
+use super::{
+    admonition::Admonition,
+    code::FancyCodeBlock,
+    headings::FancyHeading,
+    math::Math,
+    tabs::{Tab, TabGroup},
+};
 
 pub(super) fn nodes_to_string(nodes: Vec<&Node>) -> String {
     nodes
         .iter()
-        .map(|node| node.collect_text())
+        .map(|node| node_text(node))
+        .filter(|text| !text.is_empty())
         .collect::<Vec<String>>()
         .join(" ")
 }
 
+// Container blocks hold several paragraphs or list items, which need to be kept apart
+fn node_text(node: &Node) -> String {
+    let children = || nodes_to_string(node.children.iter().collect());
+
+    if let Some(admonition) = node.cast::<Admonition>() {
+        return format!("{} {}", admonition.title, children())
+            .trim_end()
+            .to_owned();
+    }
+
+    if let Some(tab) = node.cast::<Tab>() {
+        return format!("{} {}", tab.label, children())
+            .trim_end()
+            .to_owned();
+    }
+
+    // Code blocks only hold their list of annotations
+    if node.is::<FancyCodeBlock>()
+        || node.is::<TabGroup>()
+        || node.is::<OrderedList>()
+        || node.is::<BulletList>()
+    {
+        return children();
+    }
+
+    collect_text(node)
+}
+
+// Like Node::collect_text, but with math as the words in its TeX rather than left out
+fn collect_text(node: &Node) -> String {
+    let mut result = String::new();
+
+    node.walk(|node, _| {
+        if let Some(text) = node.cast::<Text>() {
+            result.push_str(text.content.as_str());
+        } else if node.is::<Softbreak>() {
+            result.push('\n');
+        } else if let Some(math) = node.cast::<Math>() {
+            result.push_str(&math.search_text());
+        }
+    });
+
+    result
+}
+
 // Convert the text before any header to a string.
 pub(super) fn preamble(node: &Node) -> String {
     let mut nodes: Vec<&Node> = Vec::new();
@@ -35,7 +101,16 @@ pub(crate) fn render(ast: &Node) -> String {
     ast.render()
 }
 
+// With every extension enabled
 pub(crate) fn ast(input: &str) -> Node {
+    ast_with_config(input, &MarkdownConfig::default(), &Highlighter::default())
+}
+
+pub(crate) fn ast_with_config(
+    input: &str,
+    config: &MarkdownConfig,
+    highlighter: &Highlighter,
+) -> Node {
     use markdown_it::plugins::cmark::{block, inline};
 
     let md = &mut markdown_it::MarkdownIt::new();
@@ -52,20 +127,23 @@ pub(crate) fn ast(input: &str) -> Node {
     inline::entity::add(md);
 
     // Replaces block::code::add(md)
-    add_code_block_rule(md);
+    add_code_block_rule(md, highlighter);
     block::fence::add(md);
     block::blockquote::add(md);
+    add_admonition_rules(md);
+    add_tabs_rule(md);
     block::hr::add(md);
     block::list::add(md);
     block::reference::add(md);
     // Replaces block::heading::add(md)
-    add_heading_rule(md);
+    add_heading_rule(md, &config.heading_anchors);
     block::lheading::add(md);
     block::paragraph::add(md);
 
     // Plugins
     use markdown_it::plugins::extra::{beautify_links, strikethrough, tables, typographer};
 
+    add_math_rules(md);
     strikethrough::add(md);
     beautify_links::add(md);
     // Disabled
@@ -77,5 +155,19 @@ pub(crate) fn ast(input: &str) -> Node {
     // Disabled (MAYBE: make this configurable?)
     // smartquotes::add(md);
 
+    // Extensions that can be turned off in jelly.yaml
+    if config.footnotes {
+        add_footnote_rules(md);
+    }
+    if config.definition_lists {
+        add_definition_list_rule(md);
+    }
+    if config.task_lists {
+        add_task_list_rule(md);
+    }
+    if config.abbreviations {
+        add_abbreviation_rules(md);
+    }
+
     md.parse(input)
 }
@@ -1,8 +1,14 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::config::SiteConfig;
 use crate::content::Link;
 use crate::content::Page;
-use crate::content::SiteAttrs;
+use crate::content::{PageSiteAttrs, SiteAttrs};
 use crate::error::JellyError;
-use handlebars::Handlebars;
+use crate::utils::get_file;
+use handlebars::template::{Parameter, TemplateElement};
+use handlebars::{handlebars_helper, Handlebars, HelperDef, Template};
 use serde::Serialize;
 
 use super::TableOfContents;
@@ -11,25 +17,34 @@ const KEY_PAGE: &str = "page";
 const KEY_SIDEBAR: &str = "sidebar";
 const KEY_TOC: &str = "toc";
 
+// Files in the templates directory with these extensions override built-ins or become partials
+const TEMPLATE_EXTENSIONS: [&str; 2] = ["hbs", "html"];
+
 #[derive(Serialize)]
-struct TemplateAttrs {
+struct TemplateAttrs<'a> {
     title: String,
+    // The URL of the page being rendered, which the sidebar highlights
+    url: &'a str,
     content: String,
     breadcrumb: Vec<Link>,
     toc: Option<TableOfContents>,
-    site: SiteAttrs,
+    has_diagrams: bool,
+    site: PageSiteAttrs<'a>,
 }
 
-impl TemplateAttrs {
+impl<'a> TemplateAttrs<'a> {
     fn new(
         title: &str,
+        url: &'a str,
         content: &str,
         breadcrumb: Vec<Link>,
         toc: TableOfContents,
-        site: SiteAttrs,
+        has_diagrams: bool,
+        site: PageSiteAttrs<'a>,
     ) -> Self {
         Self {
             title: String::from(title),
+            url,
             content: String::from(content),
             breadcrumb,
             toc: if !toc.entries.is_empty() {
@@ -37,13 +52,20 @@ impl TemplateAttrs {
             } else {
                 None
             },
+            has_diagrams,
             site,
         }
     }
 }
 
+// Whether the page at `current` is the one at `url` or somewhere under it, for templates that
+// highlight links outside of the navigation tree (which has its own active and expanded flags)
+handlebars_helper!(contains_url: |url: str, current: str| {
+    current == url || current.starts_with(&format!("{}/", url.trim_end_matches('/')))
+});
+
 #[cfg(feature = "dev-handlebars-templates")]
-fn register_templates(h: &mut Handlebars) -> Result<(), JellyError> {
+fn register_builtin_templates(h: &mut Handlebars) -> Result<(), JellyError> {
     use std::fs;
 
     h.register_template_string(
@@ -65,7 +87,7 @@ fn register_templates(h: &mut Handlebars) -> Result<(), JellyError> {
 }
 
 #[cfg(not(feature = "dev-handlebars-templates"))]
-fn register_templates(h: &mut Handlebars) -> Result<(), JellyError> {
+fn register_builtin_templates(h: &mut Handlebars) -> Result<(), JellyError> {
     h.register_template_string(
         KEY_PAGE,
         include_str!("../../../assets/templates/handlebars/page.hbs"),
@@ -84,21 +106,346 @@ fn register_templates(h: &mut Handlebars) -> Result<(), JellyError> {
     Ok(())
 }
 
+// Any template in the project's templates directory named after a built-in (page, sidebar, toc)
+// replaces it. Everything else is registered under its path relative to the directory, minus the
+// extension, so `templates/partials/footer.hbs` can be used as `{{> partials/footer}}`. Having
+// both `foo.hbs` and `foo.html` is an error since one would silently win.
+fn register_template_overrides(h: &mut Handlebars, dir: &Path) -> Result<(), JellyError> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+
+    let mut registered: HashSet<String> = HashSet::new();
+
+    for ext in TEMPLATE_EXTENSIONS {
+        let pattern = dir.join("**").join(format!("*.{ext}"));
+
+        for entry in glob::glob(&pattern.to_string_lossy())? {
+            let path = entry?;
+            let name = path
+                .strip_prefix(dir)?
+                .with_extension("")
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                .collect::<Vec<String>>()
+                .join("/");
+
+            if !registered.insert(name.clone()) {
+                return Err(JellyError::DuplicateTemplate(dir.to_path_buf(), name));
+            }
+
+            h.register_template_string(&name, get_file(&path)?)
+                .map_err(Box::new)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn register_templates(h: &mut Handlebars, config: &SiteConfig) -> Result<(), JellyError> {
+    register_builtin_templates(h)?;
+    register_template_overrides(h, &config.templates_dir)
+}
+
+// Makes sure that every partial used by a template exists so that a typo in an override shows up
+// before anything gets rendered rather than as a render error on some page.
+fn validate_partials(h: &Handlebars) -> Result<(), JellyError> {
+    let mut inline: HashSet<String> = HashSet::new();
+    let mut used: Vec<(String, String)> = Vec::new();
+
+    let mut names: Vec<&String> = h.get_templates().keys().collect();
+    names.sort();
+
+    for name in names {
+        if let Some(template) = h.get_template(name) {
+            collect_partials(name, template, &mut inline, &mut used);
+        }
+    }
+
+    for (template, partial) in used {
+        if !inline.contains(&partial) && !h.has_template(&partial) {
+            return Err(JellyError::MissingPartial(template, partial));
+        }
+    }
+
+    Ok(())
+}
+
+fn collect_partials(
+    name: &str,
+    template: &Template,
+    inline: &mut HashSet<String>,
+    used: &mut Vec<(String, String)>,
+) {
+    for element in &template.elements {
+        match element {
+            TemplateElement::PartialExpression(partial) => {
+                if let Some(partial_name) = parameter_name(&partial.name) {
+                    // @partial-block and friends are provided by Handlebars itself
+                    if !partial_name.starts_with('@') {
+                        used.push((String::from(name), partial_name));
+                    }
+                }
+            }
+            TemplateElement::DecoratorBlock(decorator) => {
+                if decorator.name.as_name() == Some("inline") {
+                    if let Some(inline_name) = decorator.params.first().and_then(parameter_name) {
+                        inline.insert(inline_name);
+                    }
+                }
+
+                if let Some(t) = &decorator.template {
+                    collect_partials(name, t, inline, used);
+                }
+            }
+            // A partial block renders its own content when the partial is missing
+            TemplateElement::PartialBlock(partial) => {
+                if let Some(t) = &partial.template {
+                    collect_partials(name, t, inline, used);
+                }
+            }
+            TemplateElement::HelperBlock(helper) => {
+                for t in [&helper.template, &helper.inverse].into_iter().flatten() {
+                    collect_partials(name, t, inline, used);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn parameter_name(parameter: &Parameter) -> Option<String> {
+    match parameter {
+        Parameter::Literal(value) => value.as_str().map(String::from),
+        _ => parameter.as_name().map(String::from),
+    }
+}
+
+// Templates are compiled once per build and the renderer is then shared by all of the threads
+// rendering pages.
+#[cfg(feature = "handlebars-templating")]
+pub(crate) struct Renderer<'reg> {
+    registry: Handlebars<'reg>,
+}
+
 #[cfg(feature = "handlebars-templating")]
-pub(crate) fn render_page(page: &Page, site: &SiteAttrs) -> Result<String, JellyError> {
-    let mut h = Handlebars::new();
-    h.set_strict_mode(false);
-    register_templates(&mut h)?;
-    let html = page.html.as_str();
-
-    let attrs = TemplateAttrs::new(
-        &page.title,
-        html,
-        page.breadcrumb.clone(),
-        page.table_of_contents.clone(),
-        site.clone(),
-    );
-
-    let s = h.render(KEY_PAGE, &attrs)?;
-    Ok(s)
+impl<'reg> Renderer<'reg> {
+    pub(crate) fn new(config: &SiteConfig) -> Result<Self, JellyError> {
+        let mut registry = Handlebars::new();
+        registry.set_strict_mode(false);
+        register_templates(&mut registry, config)?;
+        validate_partials(&registry)?;
+
+        let mut renderer = Self { registry };
+        renderer.register_helper("contains_url", Box::new(contains_url));
+        Ok(renderer)
+    }
+
+    // Hook for custom helpers. Helpers need to be thread safe since pages are rendered in parallel.
+    pub(crate) fn register_helper(
+        &mut self,
+        name: &str,
+        helper: Box<dyn HelperDef + Send + Sync + 'reg>,
+    ) {
+        self.registry.register_helper(name, helper);
+    }
+
+    pub(crate) fn render_page(&self, page: &Page, site: &SiteAttrs) -> Result<String, JellyError> {
+        let html = page.html.as_str();
+
+        let attrs = TemplateAttrs::new(
+            &page.title,
+            &page.url,
+            html,
+            page.breadcrumb.clone(),
+            page.table_of_contents.clone(),
+            page.has_diagrams,
+            site.for_page(&page.url),
+        );
+
+        let s = self.registry.render(KEY_PAGE, &attrs)?;
+        Ok(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use crate::{
+        config::{HighlightMode, SiteConfig},
+        content::Site,
+        error::JellyError,
+    };
+
+    use handlebars::handlebars_helper;
+
+    use super::Renderer;
+
+    fn config_with_templates(templates: &[(&str, &str)]) -> (SiteConfig, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+
+        for (name, content) in templates {
+            let path = dir.path().join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+
+        let mut config = SiteConfig::new(PathBuf::from("tests/full/basic"));
+        config.templates_dir = dir.path().to_path_buf();
+
+        (config, dir)
+    }
+
+    #[test]
+    fn builtin_templates_are_valid() {
+        let (config, _dir) = config_with_templates(&[]);
+        Renderer::new(&config).unwrap();
+    }
+
+    #[test]
+    fn missing_partials() {
+        let cases: Vec<(&str, Option<(&str, &str)>)> = vec![
+            ("{{> partials/footer}}", Some(("page", "partials/footer"))),
+            ("{{#if title}}{{> nope}}{{/if}}", Some(("page", "nope"))),
+            ("{{#> nope}}fallback{{/nope}}", None),
+            ("{{#*inline \"local\"}}hi{{/inline}}{{> local}}", None),
+            ("{{> sidebar site.root}}{{> toc toc}}", None),
+        ];
+
+        for (page, expected) in cases {
+            let (config, _dir) = config_with_templates(&[("page.hbs", page)]);
+
+            match (Renderer::new(&config).map(|_| ()), expected) {
+                (Ok(()), None) => {}
+                (Err(JellyError::MissingPartial(template, partial)), Some((t, p))) => {
+                    assert_eq!((template.as_str(), partial.as_str()), (t, p));
+                }
+                (result, _) => panic!("unexpected result for {page:?}: {result:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn template_overrides() {
+        let (config, _dir) = config_with_templates(&[
+            ("page.hbs", "<title>{{title}}</title>{{> partials/footer}}"),
+            ("partials/footer.html", "<footer>{{site.title}}</footer>"),
+        ]);
+        let out = tempfile::tempdir().unwrap();
+
+        Site::write(&config, out.path().to_path_buf(), false).unwrap();
+
+        let html = fs::read_to_string(out.path().join("about/index.html")).unwrap();
+        assert_eq!(html, "<title>About</title><footer>Welcome</footer>");
+    }
+
+    #[test]
+    fn sidebar_marks_current_page() {
+        let (mut config, _dir) = config_with_templates(&[("page.hbs", "{{> sidebar site.root}}")]);
+        config.root = PathBuf::from("tests/full/ordered");
+        let out = tempfile::tempdir().unwrap();
+
+        Site::write(&config, out.path().to_path_buf(), false).unwrap();
+
+        // The page and the sections leading to it, ignoring the template's indentation
+        let nav = |path: &str| {
+            let html = fs::read_to_string(out.path().join(path)).unwrap();
+            html.split_whitespace().collect::<Vec<&str>>().join(" ")
+        };
+
+        let intro = nav("beta/intro/index.html");
+        assert_eq!(intro.matches("aria-current").count(), 1);
+        assert!(intro.contains("href=\"/beta/intro\" class=\"hover:text-primary duration-hover text-primary font-semibold\" aria-current=\"page\""));
+        assert_eq!(intro.matches("<details open>").count(), 1);
+        assert!(intro.contains("<details open> <summary> <span>Beta</span>"));
+
+        let zeta = nav("zeta/index.html");
+        assert_eq!(zeta.matches("aria-current").count(), 1);
+        assert!(zeta.contains("href=\"/zeta\" class=\"hover:text-primary duration-hover text-primary font-semibold\" aria-current=\"page\""));
+        assert!(!zeta.contains("<details open>"));
+    }
+
+    #[test]
+    fn duplicate_template_overrides() {
+        let (config, _dir) =
+            config_with_templates(&[("toc.hbs", "{{title}}"), ("toc.html", "{{title}}")]);
+
+        match Renderer::new(&config) {
+            Err(JellyError::DuplicateTemplate(_, name)) => assert_eq!(name, "toc"),
+            result => panic!("unexpected result: {:?}", result.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn custom_helpers() {
+        handlebars_helper!(shout: |s: String| s.to_uppercase());
+
+        let (config, _dir) = config_with_templates(&[("page.hbs", "{{shout title}}")]);
+        let out = tempfile::tempdir().unwrap();
+
+        let mut renderer = Renderer::new(&config).unwrap();
+        renderer.register_helper("shout", Box::new(shout));
+
+        Site::write_with_renderer(&config, &renderer, out.path().to_path_buf(), false).unwrap();
+
+        let html = fs::read_to_string(out.path().join("contact/index.html")).unwrap();
+        assert_eq!(html, "CONTACT US");
+    }
+
+    #[test]
+    fn diagram_renderer_only_on_pages_with_diagrams() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(root.path().join("index.md"), "# Home\n").unwrap();
+        fs::write(
+            root.path().join("flow.md"),
+            "# Flow\n\n```mermaid\ngraph TD\n  A --> B\n```\n",
+        )
+        .unwrap();
+
+        let config = SiteConfig::new(root.path().to_path_buf());
+        let out = tempfile::tempdir().unwrap();
+
+        Site::write(&config, out.path().to_path_buf(), false).unwrap();
+
+        let flow = fs::read_to_string(out.path().join("flow/index.html")).unwrap();
+        let index = fs::read_to_string(out.path().join("index.html")).unwrap();
+        assert!(flow.contains("<pre class=\"mermaid\">"));
+        assert!(flow.contains("mermaid.initialize"));
+        assert!(!index.contains("mermaid.initialize"));
+    }
+
+    #[test]
+    fn highlighting_stylesheet() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(
+            root.path().join("index.md"),
+            "# Home\n\n```rust\nlet x = 1;\n```\n",
+        )
+        .unwrap();
+
+        let mut config = SiteConfig::new(root.path().to_path_buf());
+        let out = tempfile::tempdir().unwrap();
+
+        Site::write(&config, out.path().to_path_buf(), false).unwrap();
+
+        let index = fs::read_to_string(out.path().join("index.html")).unwrap();
+        let css = fs::read_to_string(out.path().join("highlighting.css")).unwrap();
+        assert!(index.contains("<link rel=\"stylesheet\" href=\"/highlighting.css\" />"));
+        assert!(index.contains("<span class=\"source rust\">"));
+        assert!(css.contains("html.dark pre[class*=\"language-\"]"));
+
+        config.highlighting.mode = HighlightMode::Inline;
+        let out = tempfile::tempdir().unwrap();
+
+        Site::write(&config, out.path().to_path_buf(), false).unwrap();
+
+        let index = fs::read_to_string(out.path().join("index.html")).unwrap();
+        assert!(!out.path().join("highlighting.css").exists());
+        assert!(!index.contains("highlighting.css"));
+        assert!(index.contains(
+            "<pre class=\"language-rust\" style=\"background-color: #ffffff; color: #323232\">"
+        ));
+        assert!(!index.contains("<span class=\"source rust\">"));
+    }
 }
@@ -5,16 +5,39 @@ use super::{headings::HeadingsWithTextAfter, parse::preamble};
 
 #[derive(Clone, Debug, Eq, PartialEq, Serialize)]
 pub(crate) struct SearchDocument {
+    // Unique within the site: the page URL plus the heading slug (if any)
+    id: String,
     level: u8,
+    url: String,
+    slug: Option<String>,
+    breadcrumb: Vec<String>,
     page_title: String,
     title: String,
     content: String,
 }
 
 impl SearchDocument {
-    pub(crate) fn new(level: u8, page_title: &str, title: &str, content: &str) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        level: u8,
+        url: &str,
+        slug: Option<&str>,
+        breadcrumb: &[String],
+        page_title: &str,
+        title: &str,
+        content: &str,
+    ) -> Self {
+        let id = match slug {
+            Some(slug) => format!("{url}#{slug}"),
+            None => String::from(url),
+        };
+
         Self {
+            id,
             level,
+            url: String::from(url),
+            slug: slug.map(String::from),
+            breadcrumb: breadcrumb.to_vec(),
             page_title: String::from(page_title),
             title: String::from(title),
             content: String::from(content),
@@ -25,11 +48,19 @@ impl SearchDocument {
 #[derive(Clone, Debug, Eq, PartialEq, Serialize)]
 pub(crate) struct SearchIndex(pub(crate) Vec<SearchDocument>);
 
-pub(crate) fn build_search_index_for_page(page_title: &str, document: &Node) -> SearchIndex {
+pub(crate) fn build_search_index_for_page(
+    page_title: &str,
+    url: &str,
+    breadcrumb: &[String],
+    document: &Node,
+) -> SearchIndex {
     let mut documents: Vec<SearchDocument> = Vec::new();
 
     documents.push(SearchDocument::new(
         1,
+        url,
+        None,
+        breadcrumb,
         page_title,
         page_title,
         &preamble(document),
@@ -38,6 +69,9 @@ pub(crate) fn build_search_index_for_page(page_title: &str, document: &Node) ->
     for (heading, s) in HeadingsWithTextAfter(&document.children) {
         documents.push(SearchDocument::new(
             heading.level,
+            url,
+            Some(&heading.slug),
+            breadcrumb,
             page_title,
             &heading.text,
             &s,
@@ -57,14 +91,26 @@ mod tests {
 
     #[test]
     fn search_index() {
-        let cases: Vec<(&str, &str, SearchIndex)> = vec![
+        let trail = vec![String::from("Docs")];
+
+        let cases: Vec<(&str, &str, &str, SearchIndex)> = vec![
             (
                 "First page",
+                "/first",
                 "",
-                SearchIndex(vec![SearchDocument::new(1, "First page", "First page", "")]),
+                SearchIndex(vec![SearchDocument::new(
+                    1,
+                    "/first",
+                    None,
+                    &trail,
+                    "First page",
+                    "First page",
+                    "",
+                )]),
             ),
             (
                 "Second page",
+                "/guides/second",
                 indoc! {"
                     Some text.
 
@@ -79,10 +125,29 @@ mod tests {
                     And some text from another paragraph.
                 "},
                 SearchIndex(vec![
-                    SearchDocument::new(1, "Second page", "Second page", "Some text."),
-                    SearchDocument::new(2, "Second page", "h2", "Some text content."),
+                    SearchDocument::new(
+                        1,
+                        "/guides/second",
+                        None,
+                        &trail,
+                        "Second page",
+                        "Second page",
+                        "Some text.",
+                    ),
+                    SearchDocument::new(
+                        2,
+                        "/guides/second",
+                        Some("h2"),
+                        &trail,
+                        "Second page",
+                        "h2",
+                        "Some text content.",
+                    ),
                     SearchDocument::new(
                         3,
+                        "/guides/second",
+                        Some("h3"),
+                        &trail,
                         "Second page",
                         "h3",
                         "And some more. And some text from another paragraph.",
@@ -91,12 +156,43 @@ mod tests {
             ),
         ];
 
-        for (page_title, md, expected_index) in cases {
+        for (page_title, url, md, expected_index) in cases {
             let tree = ast(md);
-            let index = build_search_index_for_page(page_title, &tree);
-            for (idx, doc) in index.0.iter().enumerate() {
-                assert_eq!(doc, &expected_index.0[idx]);
-            }
+            let index = build_search_index_for_page(page_title, url, &trail, &tree);
+            assert_eq!(index, expected_index);
         }
     }
+
+    #[test]
+    fn heading_with_code() {
+        let tree = ast(indoc! {"
+            ## Calling `build`
+
+            Builds the site.
+        "});
+        let index = build_search_index_for_page("API", "/api", &[], &tree);
+
+        assert_eq!(
+            index.0[1],
+            SearchDocument::new(
+                2,
+                "/api",
+                Some("calling-build"),
+                &[],
+                "API",
+                "Calling build",
+                "Builds the site.",
+            )
+        );
+    }
+
+    #[test]
+    fn document_ids() {
+        let page = SearchDocument::new(1, "/setup", None, &[], "Setup", "Setup", "");
+        let heading =
+            SearchDocument::new(2, "/setup", Some("install"), &[], "Setup", "Install", "");
+
+        assert_eq!(page.id, "/setup");
+        assert_eq!(heading.id, "/setup#install");
+    }
 }
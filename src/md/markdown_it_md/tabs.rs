@@ -0,0 +1,272 @@
+use markdown_it::{
+    parser::{
+        block::{BlockRule, BlockState},
+        core::CoreRule,
+    },
+    plugins::cmark::block::blockquote::BlockquoteScanner,
+    MarkdownIt, Node, NodeValue, Renderer,
+};
+
+use super::admonition::{indented_block_end, tokenize_inside};
+
+const CONTENT_INDENT: i32 = 4;
+
+// Consecutive `=== "Label"` blocks, each followed by content indented by four spaces
+#[derive(Debug)]
+pub(super) struct TabGroup {
+    // Unique within the page, for the ids that tie tabs to their panels
+    number: usize,
+}
+
+#[derive(Debug)]
+pub(super) struct Tab {
+    pub(super) label: String,
+}
+
+impl TabGroup {
+    fn labels(node: &Node) -> Vec<&str> {
+        node.children
+            .iter()
+            .filter_map(|child| child.cast::<Tab>())
+            .map(|tab| tab.label.as_str())
+            .collect()
+    }
+}
+
+impl NodeValue for TabGroup {
+    fn render(&self, node: &Node, fmt: &mut dyn Renderer) {
+        let labels = TabGroup::labels(node);
+        let id = |kind: &str, idx: usize| format!("tabs-{}-{kind}-{}", self.number, idx + 1);
+
+        // Groups with the same tabs are kept in sync by the page template
+        let group_attrs = vec![
+            ("class", String::from("tabs")),
+            ("data-tabs", labels.join("|")),
+        ];
+
+        fmt.cr();
+        fmt.open("div", &group_attrs);
+        fmt.cr();
+        fmt.open(
+            "div",
+            &[
+                ("class", String::from("tab-list")),
+                ("role", String::from("tablist")),
+            ],
+        );
+        for (idx, label) in labels.iter().enumerate() {
+            let selected = idx == 0;
+            let tab_attrs = vec![
+                ("type", String::from("button")),
+                ("role", String::from("tab")),
+                ("id", id("tab", idx)),
+                ("aria-controls", id("panel", idx)),
+                ("aria-selected", selected.to_string()),
+                ("tabindex", String::from(if selected { "0" } else { "-1" })),
+                ("data-tab", String::from(*label)),
+            ];
+
+            fmt.open("button", &tab_attrs);
+            fmt.text(label);
+            fmt.close("button");
+        }
+        fmt.close("div");
+        fmt.cr();
+
+        for (idx, panel) in node.children.iter().enumerate() {
+            let mut panel_attrs = vec![
+                ("class", String::from("tab-panel")),
+                ("role", String::from("tabpanel")),
+                ("id", id("panel", idx)),
+                ("aria-labelledby", id("tab", idx)),
+                ("tabindex", String::from("0")),
+            ];
+            if idx > 0 {
+                panel_attrs.push(("hidden", String::new()));
+            }
+
+            fmt.open("div", &panel_attrs);
+            fmt.cr();
+            fmt.contents(&panel.children);
+            fmt.cr();
+            fmt.close("div");
+            fmt.cr();
+        }
+
+        fmt.close("div");
+        fmt.cr();
+    }
+}
+
+// Tabs are rendered by their group
+impl NodeValue for Tab {
+    fn render(&self, node: &Node, fmt: &mut dyn Renderer) {
+        fmt.contents(&node.children);
+    }
+}
+
+struct TabScanner;
+
+impl TabScanner {
+    fn label(line: &str) -> Option<String> {
+        let rest = line.strip_prefix("===")?;
+
+        if !rest.starts_with([' ', '\t']) {
+            return None;
+        }
+
+        let label = rest.trim().trim_matches('"').trim();
+
+        if label.is_empty() {
+            None
+        } else {
+            Some(String::from(label))
+        }
+    }
+}
+
+impl BlockRule for TabScanner {
+    fn check(state: &mut BlockState) -> Option<()> {
+        if state.line_indent(state.line) >= state.md.max_indent {
+            return None;
+        }
+
+        Self::label(state.get_line(state.line)).map(|_| ())
+    }
+
+    fn run(state: &mut BlockState) -> Option<(Node, usize)> {
+        Self::check(state)?;
+
+        let start_line = state.line;
+        let indent = state.line_offsets[start_line].indent_nonspace;
+        let content_indent = indent + CONTENT_INDENT;
+        let mut group = Node::new(TabGroup { number: 0 });
+        let mut line = start_line;
+
+        loop {
+            let label = Self::label(state.get_line(line))?;
+            let end_line = indented_block_end(state, line, content_indent);
+
+            let old_indent = state.blk_indent;
+            state.blk_indent = content_indent as usize;
+            group
+                .children
+                .push(tokenize_inside(state, Tab { label }, line + 1, end_line));
+            state.blk_indent = old_indent;
+
+            // The group goes on for as long as tabs follow each other
+            let next_line = state.skip_empty_lines(end_line);
+            let is_tab = next_line < state.line_max
+                && state.line_offsets[next_line].indent_nonspace == indent
+                && Self::label(state.get_line(next_line)).is_some();
+
+            if !is_tab {
+                return Some((group, end_line - start_line));
+            }
+
+            line = next_line;
+        }
+    }
+}
+
+struct NumberTabGroupsRule;
+
+impl CoreRule for NumberTabGroupsRule {
+    fn run(root: &mut Node, _: &MarkdownIt) {
+        let mut count = 0;
+
+        root.walk_mut(|node, _| {
+            if let Some(group) = node.cast_mut::<TabGroup>() {
+                count += 1;
+                group.number = count;
+            }
+        });
+    }
+}
+
+pub(super) fn add_tabs_rule(md: &mut MarkdownIt) {
+    md.block
+        .add_rule::<TabScanner>()
+        .before::<BlockquoteScanner>();
+    md.add_rule::<NumberTabGroupsRule>();
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::{
+        md::{ast, build_search_index_for_page, TableOfContents},
+        tests::test_markdown_produces_expected_html,
+    };
+
+    #[test]
+    fn tabs() {
+        let cases: Vec<(&str, &str)> = vec![
+            (
+                indoc! {r#"
+                    === "Rust"
+                        Use `cargo`.
+
+                    === "Python"
+
+                        Use `pip`.
+
+                        - Or `uv`
+
+                    After the tabs.
+
+                    === Rust
+                        Another group.
+                "#},
+                indoc! {r#"
+                    <div class="tabs" data-tabs="Rust|Python">
+                    <div class="tab-list" role="tablist"><button type="button" role="tab" id="tabs-1-tab-1" aria-controls="tabs-1-panel-1" aria-selected="true" tabindex="0" data-tab="Rust">Rust</button><button type="button" role="tab" id="tabs-1-tab-2" aria-controls="tabs-1-panel-2" aria-selected="false" tabindex="-1" data-tab="Python">Python</button></div>
+                    <div class="tab-panel" role="tabpanel" id="tabs-1-panel-1" aria-labelledby="tabs-1-tab-1" tabindex="0">
+                    <p>Use <code>cargo</code>.</p>
+                    </div>
+                    <div class="tab-panel" role="tabpanel" id="tabs-1-panel-2" aria-labelledby="tabs-1-tab-2" tabindex="0" hidden="">
+                    <p>Use <code>pip</code>.</p>
+                    <ul>
+                    <li>Or <code>uv</code></li>
+                    </ul>
+                    </div>
+                    </div>
+                    <p>After the tabs.</p>
+                    <div class="tabs" data-tabs="Rust">
+                    <div class="tab-list" role="tablist"><button type="button" role="tab" id="tabs-2-tab-1" aria-controls="tabs-2-panel-1" aria-selected="true" tabindex="0" data-tab="Rust">Rust</button></div>
+                    <div class="tab-panel" role="tabpanel" id="tabs-2-panel-1" aria-labelledby="tabs-2-tab-1" tabindex="0">
+                    <p>Another group.</p>
+                    </div>
+                    </div>
+                "#},
+            ),
+            ("===\n", "<p>===</p>\n"),
+            ("===Rust\n", "<p>===Rust</p>\n"),
+        ];
+
+        test_markdown_produces_expected_html(cases);
+    }
+
+    #[test]
+    fn tabs_in_search_and_toc() {
+        let tree = ast(indoc! {r#"
+            ## Install
+
+            === "Rust"
+                ## Not in the TOC
+
+                Use cargo.
+
+            === "Python"
+                Use pip.
+        "#});
+
+        assert_eq!(TableOfContents::parse(&tree).slugs(), vec!["install"]);
+
+        let index = build_search_index_for_page("Guide", "/guide", &[], &tree);
+        assert!(
+            format!("{:?}", index.0[1]).contains("Rust Not in the TOC Use cargo. Python Use pip.")
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use markdown_it::{
+    parser::{core::CoreRule, inline::Text},
+    plugins::cmark::block::{
+        list::{BulletList, OrderedList},
+        paragraph::Paragraph,
+    },
+    MarkdownIt, Node, NodeValue, Renderer,
+};
+
+// GitHub-style `- [ ]` and `- [x]` list items
+#[derive(Debug)]
+struct TaskCheckbox {
+    checked: bool,
+}
+
+impl NodeValue for TaskCheckbox {
+    fn render(&self, _: &Node, fmt: &mut dyn Renderer) {
+        let mut attrs = vec![
+            ("type", String::from("checkbox")),
+            ("class", String::from("task-list-item-checkbox")),
+            ("disabled", String::new()),
+        ];
+        if self.checked {
+            attrs.push(("checked", String::new()));
+        }
+
+        fmt.self_close("input", &attrs);
+    }
+}
+
+// Replaces the `[ ]` or `[x]` at the start of a list item with a checkbox
+fn add_checkbox(item: &mut Node) -> bool {
+    // Loose list items hold a paragraph, tight ones hold its contents
+    let inline = match item.children.first() {
+        Some(first) if first.is::<Paragraph>() => &mut item.children[0].children,
+        _ => &mut item.children,
+    };
+
+    let Some(text) = inline.first_mut().and_then(|node| node.cast_mut::<Text>()) else {
+        return false;
+    };
+
+    let checked = match text.content.get(..3) {
+        Some("[ ]") => false,
+        Some("[x]" | "[X]") => true,
+        _ => return false,
+    };
+
+    if !text.content[3..].starts_with([' ', '\t']) {
+        return false;
+    }
+
+    text.content.replace_range(..3, "");
+    inline.insert(0, Node::new(TaskCheckbox { checked }));
+
+    true
+}
+
+struct TaskListRule;
+
+impl CoreRule for TaskListRule {
+    fn run(root: &mut Node, _: &MarkdownIt) {
+        root.walk_mut(|node, _| {
+            if !node.is::<BulletList>() && !node.is::<OrderedList>() {
+                return;
+            }
+
+            let mut has_tasks = false;
+            for item in node.children.iter_mut() {
+                if add_checkbox(item) {
+                    item.attrs.push(("class", String::from("task-list-item")));
+                    has_tasks = true;
+                }
+            }
+
+            if has_tasks {
+                node.attrs
+                    .push(("class", String::from("contains-task-list")));
+            }
+        });
+    }
+}
+
+pub(super) fn add_task_list_rule(md: &mut MarkdownIt) {
+    md.add_rule::<TaskListRule>();
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::tests::test_markdown_produces_expected_html;
+
+    #[test]
+    fn task_lists() {
+        let cases: Vec<(&str, &str)> = vec![
+            (
+                indoc! {"
+                    - [ ] To do
+                    - [x] Done
+                    - [y] Not a task
+                    - [ ]
+                "},
+                indoc! {r#"
+                    <ul class="contains-task-list">
+                    <li class="task-list-item"><input type="checkbox" class="task-list-item-checkbox" disabled=""> To do</li>
+                    <li class="task-list-item"><input type="checkbox" class="task-list-item-checkbox" disabled="" checked=""> Done</li>
+                    <li>[y] Not a task</li>
+                    <li>[ ]</li>
+                    </ul>
+                "#},
+            ),
+            (
+                indoc! {"
+                    1. [X] Loose
+
+                    2. Regular
+                "},
+                indoc! {r#"
+                    <ol class="contains-task-list">
+                    <li class="task-list-item">
+                    <p><input type="checkbox" class="task-list-item-checkbox" disabled="" checked=""> Loose</p>
+                    </li>
+                    <li>
+                    <p>Regular</p>
+                    </li>
+                    </ol>
+                "#},
+            ),
+            ("- Plain\n", "<ul>\n<li>Plain</li>\n</ul>\n"),
+        ];
+
+        test_markdown_produces_expected_html(cases);
+    }
+}
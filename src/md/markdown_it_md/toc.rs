@@ -5,9 +5,8 @@
 
 use markdown_it::Node;
 use serde::Serialize;
-use slug::slugify;
 
-use super::headings::Headings;
+use super::headings::{Heading, Headings};
 
 #[derive(Clone, Debug, Eq, PartialEq, Serialize)]
 pub(crate) struct TableOfContents {
@@ -19,12 +18,25 @@ impl TableOfContents {
         let mut builder = TocBuilder::new();
 
         for heading in Headings(&document.children) {
-            builder.push(heading.level, heading.text);
+            builder.push(heading);
         }
 
         builder.into_toc()
     }
 
+    // The slugs of all of the headings, including nested ones
+    #[cfg(test)]
+    pub(crate) fn slugs(&self) -> Vec<&str> {
+        let mut slugs: Vec<&str> = Vec::new();
+
+        for entry in &self.entries {
+            slugs.push(&entry.slug);
+            slugs.extend(entry.children.slugs());
+        }
+
+        slugs
+    }
+
     pub(crate) fn empty() -> Self {
         Self { entries: vec![] }
     }
@@ -44,12 +56,12 @@ pub(crate) struct TocEntry {
 }
 
 impl TocEntry {
+    #[cfg(test)]
     pub(crate) fn new(level: u8, text: &str, children: TableOfContents) -> Self {
-        let slug = slugify(text);
         Self {
             level,
             text: String::from(text),
-            slug,
+            slug: slug::slugify(text),
             children,
         }
     }
@@ -97,13 +109,17 @@ impl TocBuilder {
         }
     }
 
-    fn push(&mut self, level: u8, text: String) {
-        assert!(level >= 2);
+    fn push(&mut self, heading: Heading) {
+        assert!(heading.level >= 2);
 
-        self.fold_until(level);
+        self.fold_until(heading.level);
 
-        self.chain
-            .push(TocEntry::new(level, &text, TableOfContents::empty()));
+        self.chain.push(TocEntry {
+            level: heading.level,
+            text: heading.text,
+            slug: heading.slug,
+            children: TableOfContents::empty(),
+        });
     }
 }
 
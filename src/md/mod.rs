@@ -1,9 +1,11 @@
 #[cfg(feature = "markdown-it-md")]
 mod markdown_it_md;
 pub(super) use markdown_it_md::{
-    ast, build_search_index_for_page, get_document_title, render, render_page, SearchDocument,
-    SearchIndex, TableOfContents,
+    anchor_ids, assign_callout_ids, ast_with_config, build_search_index_for_page,
+    collect_references, duplicate_heading_ids, get_document_title, has_diagrams, highlighting_css,
+    include_code, math_errors, render, resolve_image_urls, resolve_links, unknown_languages,
+    Highlighter, Reference, ReferenceKind, Renderer, SearchDocument, SearchIndex, TableOfContents,
 };
 
 #[cfg(all(test, feature = "markdown-it-md"))]
-pub(super) use markdown_it_md::TocEntry;
+pub(super) use markdown_it_md::{ast, TocEntry};
@@ -41,6 +41,78 @@ pub(super) fn vec_or_none<T>(items: Vec<T>) -> Option<Vec<T>> {
     }
 }
 
+// Links to other sites, the site root, or anchors on the same page are left alone
+pub(super) fn is_relative_url(url: &str) -> bool {
+    let path_end = url.find(['/', '?', '#']).unwrap_or(url.len());
+
+    !url.is_empty() && !url.starts_with(['/', '#', '?']) && !url[..path_end].contains(':')
+}
+
+// Resolves a relative URL against a directory URL such as /setup, keeping any query string or
+// fragment intact and collapsing . and .. segments.
+pub(super) fn join_url(base: &str, relative: &str) -> String {
+    let suffix_start = relative.find(['?', '#']).unwrap_or(relative.len());
+    let (path, suffix) = relative.split_at(suffix_start);
+
+    let mut segments: Vec<&str> = base.split('/').filter(|s| !s.is_empty()).collect();
+
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            s => segments.push(s),
+        }
+    }
+
+    let trailing_slash = if path.ends_with('/') && !segments.is_empty() {
+        "/"
+    } else {
+        ""
+    };
+
+    format!("/{}{trailing_slash}{suffix}", segments.join("/"))
+}
+
 fn capitalize_first_letter(s: &str) -> String {
     s[0..1].to_uppercase() + &s[1..]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{is_relative_url, join_url};
+
+    #[test]
+    fn relative_urls() {
+        for url in ["foo.png", "./foo.png", "../foo/bar.md#baz", "foo/a:b"] {
+            assert!(is_relative_url(url), "{url}");
+        }
+
+        for url in [
+            "",
+            "/foo.png",
+            "#anchor",
+            "https://example.com",
+            "mailto:me@example.com",
+        ] {
+            assert!(!is_relative_url(url), "{url}");
+        }
+    }
+
+    #[test]
+    fn join_urls() {
+        let cases: Vec<(&str, &str, &str)> = vec![
+            ("/", "foo.png", "/foo.png"),
+            ("/setup", "diagram.svg", "/setup/diagram.svg"),
+            ("/setup", "./img/diagram.svg", "/setup/img/diagram.svg"),
+            ("/setup/advanced", "../other#anchor", "/setup/other#anchor"),
+            ("/setup", "../../../foo", "/foo"),
+            ("/setup", "files/?download=1", "/setup/files/?download=1"),
+        ];
+
+        for (base, relative, expected) in cases {
+            assert_eq!(join_url(base, relative), expected);
+        }
+    }
+}
@@ -0,0 +1,8 @@
+use std::env;
+
+fn main() {
+    // ANCHOR: setup
+    let config = env::args().nth(1);
+    // ANCHOR_END: setup
+    println!("{config:?}");
+}